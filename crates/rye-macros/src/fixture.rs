@@ -0,0 +1,256 @@
+use crate::test::Errors;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens, TokenStreamExt as _};
+use syn::{
+    ext::IdentExt as _,
+    parse::{Error, ParseStream, Result},
+    Attribute, Ident, ItemFn, Path, ReturnType, Token, Type,
+};
+
+macro_rules! try_quote {
+    ($e:expr) => {
+        match $e {
+            Ok(parsed) => parsed,
+            Err(err) => return err.to_compile_error(),
+        }
+    };
+}
+
+pub(crate) fn fixture(args: TokenStream, item: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return Error::new_spanned(&args, "no arguments are accepted").to_compile_error();
+    }
+
+    let mut item = try_quote!(syn::parse2::<ItemFn>(item));
+
+    if !item.sig.generics.params.is_empty() {
+        return Error::new_spanned(
+            &item.sig.generics,
+            "fixture functions cannot take generic parameters",
+        )
+        .to_compile_error();
+    }
+    if !item.sig.inputs.is_empty() {
+        return Error::new_spanned(
+            &item.sig.inputs,
+            "fixture functions cannot take any parameters",
+        )
+        .to_compile_error();
+    }
+
+    let params = try_quote!(Params::from_attrs(&mut item.attrs));
+
+    Generated {
+        item: &item,
+        params: &params,
+    }
+    .to_token_stream()
+}
+
+enum Scope {
+    Section,
+    Test,
+    Session,
+}
+
+struct Params {
+    crate_path: Path,
+    scope: Scope,
+    teardown: Option<Path>,
+}
+
+impl Params {
+    fn from_attrs(attrs: &mut Vec<Attribute>) -> Result<Self> {
+        let mut crate_path = None;
+        let mut scope = None;
+        let mut teardown = None;
+
+        let mut parse_attr = |input: ParseStream<'_>| -> Result<()> {
+            match input.call(Ident::parse_any)? {
+                id if id == "crate" => {
+                    let _: Token![=] = input.parse()?;
+                    let path = input.call(Path::parse_mod_style)?;
+                    crate_path.replace(path);
+                    Ok(())
+                }
+                id if id == "scope" => {
+                    let _: Token![=] = input.parse()?;
+                    let value: Ident = input.parse()?;
+                    let parsed = match value {
+                        ref value if value == "section" => Scope::Section,
+                        ref value if value == "test" => Scope::Test,
+                        ref value if value == "session" => Scope::Session,
+                        value => {
+                            return Err(Error::new_spanned(
+                                value,
+                                "the scope must be one of `section`, `test` or `session`",
+                            ))
+                        }
+                    };
+                    scope.replace(parsed);
+                    Ok(())
+                }
+                id if id == "teardown" => {
+                    let _: Token![=] = input.parse()?;
+                    let path: Path = input.parse()?;
+                    teardown.replace(path);
+                    Ok(())
+                }
+                id => Err(Error::new_spanned(id, "unknown parameter name")),
+            }
+        };
+
+        let mut errors = Errors::default();
+        attrs.retain(|attr| {
+            if !attr.path.is_ident("rye") {
+                return true;
+            }
+            errors.append_if_error(attr.parse_args_with(&mut parse_attr));
+            false
+        });
+        errors.into_result()?;
+
+        Ok(Self {
+            crate_path: crate_path.unwrap_or_else(|| syn::parse_quote!(::rye)),
+            scope: scope.unwrap_or(Scope::Section),
+            teardown,
+        })
+    }
+}
+
+struct Generated<'a> {
+    item: &'a ItemFn,
+    params: &'a Params,
+}
+
+impl ToTokens for Generated<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let crate_path = &self.params.crate_path;
+        let item = self.item;
+        let ident = &item.sig.ident;
+        let vis = &item.vis;
+
+        let output: Type = match item.sig.output {
+            ReturnType::Default => syn::parse_quote!(()),
+            ReturnType::Type(_, ref ty) => (**ty).clone(),
+        };
+
+        let (scope, into_value, from_value) = match self.params.scope {
+            Scope::Section => (quote!(Section), quote!(owned), quote!(into_owned)),
+            Scope::Test => (quote!(Test), quote!(owned), quote!(into_owned)),
+            Scope::Session => (quote!(Session), quote!(shared), quote!(into_shared)),
+        };
+
+        let (setup_fn, setup) = if item.sig.asyncness.is_some() {
+            (
+                quote! {
+                    fn __rye_setup() -> __rye::BoxFuture<'static, __rye::FixtureValue> {
+                        __rye::Box::pin(async {
+                            __rye::FixtureValue::#into_value(#ident().await)
+                        })
+                    }
+                },
+                quote!(__rye::FixtureSetup::Async(__rye_setup)),
+            )
+        } else {
+            (
+                quote! {
+                    fn __rye_setup() -> __rye::FixtureValue {
+                        __rye::FixtureValue::#into_value(#ident())
+                    }
+                },
+                quote!(__rye::FixtureSetup::Blocking(__rye_setup)),
+            )
+        };
+
+        let (teardown_fn, teardown) = match self.params.teardown {
+            Some(ref path) => (
+                quote! {
+                    fn __rye_teardown(
+                        value: __rye::FixtureValue,
+                    ) -> __rye::BoxFuture<'static, ()> {
+                        __rye::Box::pin(async move {
+                            if let __rye::Option::Some(value) = value.#from_value::<#output>() {
+                                #path(value).await;
+                            }
+                        })
+                    }
+                },
+                quote!(__rye::Option::Some(__rye_teardown)),
+            ),
+            None => (quote!(), quote!(__rye::Option::None)),
+        };
+
+        tokens.append_all(Some(quote! {
+            #item
+
+            #[allow(non_camel_case_types, dead_code)]
+            #vis struct #ident {}
+
+            const _: () = {
+                #[allow(unused_imports)]
+                use #crate_path::_test_reexports as __rye;
+
+                #setup_fn
+
+                #teardown_fn
+
+                impl __rye::Fixture for #ident {
+                    type Output = #output;
+
+                    const DESC: __rye::FixtureDesc = __rye::FixtureDesc {
+                        name: __rye::stringify!(#ident),
+                        type_id: __rye::TypeId::of::<#ident>,
+                        scope: __rye::FixtureScope::#scope,
+                        setup: #setup,
+                        teardown: #teardown,
+                    };
+                }
+            };
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use syn::{parse::Parser as _, Item};
+
+    fn parse_items(input: ParseStream) -> Result<Vec<Item>> {
+        let mut items = vec![];
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(items)
+    }
+
+    fn read_file<P: AsRef<Path>>(path: P) -> TokenStream {
+        let content = std::fs::read_to_string(path).unwrap();
+        let items = parse_items.parse_str(&content).unwrap();
+        quote!(#(#items)*)
+    }
+
+    fn test_expanded(name: &str) {
+        let args = TokenStream::new();
+        let item = read_file(format!("tests/fixture/{}.in.rs", name));
+        let expected = read_file(format!("tests/fixture/{}.out.rs", name));
+        let output = fixture(args, item);
+        assert_eq!(expected.to_string(), output.to_string());
+    }
+
+    #[test]
+    fn blocking() {
+        test_expanded("01-blocking");
+    }
+
+    #[test]
+    fn async_teardown() {
+        test_expanded("02-async-teardown");
+    }
+
+    #[test]
+    fn session_scope() {
+        test_expanded("03-session-scope");
+    }
+}
@@ -1,5 +1,6 @@
 extern crate proc_macro;
 
+mod fixture;
 mod test;
 mod test_main;
 
@@ -14,3 +15,8 @@ pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
 pub fn test_main(args: TokenStream, item: TokenStream) -> TokenStream {
     crate::test_main::test_main(args.into(), item.into()).into()
 }
+
+#[proc_macro_attribute]
+pub fn fixture(args: TokenStream, item: TokenStream) -> TokenStream {
+    crate::fixture::fixture(args.into(), item.into()).into()
+}
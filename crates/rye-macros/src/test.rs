@@ -1,13 +1,18 @@
 use indexmap::IndexMap;
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Span, TokenStream, TokenTree};
 use quote::{quote, quote_spanned, ToTokens, TokenStreamExt as _};
 use std::mem;
 use syn::{
     ext::IdentExt as _,
     parse::{Error, Parse, ParseStream, Result},
     spanned::Spanned as _,
+    token,
+    visit::{self, Visit},
     visit_mut::{self, VisitMut},
-    Attribute, Block, Expr, ExprMacro, Ident, Item, ItemFn, ItemMacro, Macro, Path, Stmt, Token,
+    Attribute, BinOp, Block, Expr, ExprAsync, ExprAwait, ExprBinary, ExprBlock, ExprCall,
+    ExprClosure, ExprForLoop, ExprIf, ExprMacro, ExprMatch, ExprParen, ExprPath, ExprWhile, FnArg,
+    Ident, Item, ItemFn, ItemMacro, LitInt, LitStr, Local, Macro, Pat, PatIdent, Path, Stmt, Token,
+    Type,
 };
 
 macro_rules! try_quote {
@@ -38,6 +43,9 @@ pub(crate) fn test(args: TokenStream, item: TokenStream) -> TokenStream {
     // extract rye-specific attributes.
     let params = try_quote!(Params::from_attrs(&mut item.attrs));
 
+    // extract the fixtures requested by the function parameters.
+    let fixtures = try_quote!(extract_fixtures(&mut item));
+
     // expand section!()
     let sections = expand_builtins(&mut item);
 
@@ -54,6 +62,7 @@ pub(crate) fn test(args: TokenStream, item: TokenStream) -> TokenStream {
         params: &params,
         args: &args,
         sections: &sections,
+        fixtures: &fixtures,
     }
     .to_token_stream()
 }
@@ -100,11 +109,34 @@ impl Parse for Args {
 
 struct Params {
     crate_path: Path,
+    globals: Vec<Type>,
+    isolate: bool,
+    retries: Option<LitInt>,
+    resources: Vec<Resource>,
+    skip_conditions: Vec<SkipCondition>,
+    xfail: Option<LitStr>,
+}
+
+enum Resource {
+    Serial,
+    Named(LitStr),
+}
+
+enum SkipCondition {
+    If(Path),
+    Env(LitStr),
+    Cmd(LitStr),
 }
 
 impl Params {
     fn from_attrs(attrs: &mut Vec<Attribute>) -> Result<Self> {
         let mut crate_path = None;
+        let mut globals = vec![];
+        let mut isolate = false;
+        let mut retries = None;
+        let mut resources = vec![];
+        let mut skip_conditions = vec![];
+        let mut xfail = None;
 
         let mut parse_attr = |input: ParseStream<'_>| -> Result<()> {
             match input.call(Ident::parse_any)? {
@@ -114,6 +146,65 @@ impl Params {
                     crate_path.replace(path);
                     Ok(())
                 }
+                id if id == "global" => {
+                    let _: Token![=] = input.parse()?;
+                    let ty: Type = input.parse()?;
+                    globals.push(ty);
+                    Ok(())
+                }
+                id if id == "isolate" => {
+                    isolate = true;
+                    Ok(())
+                }
+                id if id == "retries" => {
+                    let _: Token![=] = input.parse()?;
+                    let n: LitInt = input.parse()?;
+                    n.base10_parse::<u32>()?;
+                    retries.replace(n);
+                    Ok(())
+                }
+                id if id == "serial" => {
+                    // The test cases marked as `serial` without the group name
+                    // share the anonymous resource.
+                    if !input.peek(token::Paren) {
+                        resources.push(Resource::Serial);
+                        return Ok(());
+                    }
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let key = content.call(Ident::parse_any)?;
+                    if key != "group" {
+                        return Err(Error::new_spanned(key, "expected `group`"));
+                    }
+                    let _: Token![=] = content.parse()?;
+                    resources.push(Resource::Named(content.call(parse_resource_name)?));
+                    Ok(())
+                }
+                id if id == "resource" => {
+                    let _: Token![=] = input.parse()?;
+                    resources.push(Resource::Named(input.call(parse_resource_name)?));
+                    Ok(())
+                }
+                id if id == "skip_if" => {
+                    let _: Token![=] = input.parse()?;
+                    skip_conditions.push(SkipCondition::If(input.parse()?));
+                    Ok(())
+                }
+                id if id == "require_env" => {
+                    let _: Token![=] = input.parse()?;
+                    skip_conditions.push(SkipCondition::Env(input.parse()?));
+                    Ok(())
+                }
+                id if id == "require_cmd" => {
+                    let _: Token![=] = input.parse()?;
+                    skip_conditions.push(SkipCondition::Cmd(input.parse()?));
+                    Ok(())
+                }
+                id if id == "xfail" => {
+                    let _: Token![=] = input.parse()?;
+                    xfail.replace(input.parse()?);
+                    Ok(())
+                }
                 id => Err(Error::new_spanned(id, "unknown parameter name")),
             }
         };
@@ -128,17 +219,114 @@ impl Params {
         });
         errors.into_result()?;
 
+        // The test cases expected to fail are never retried.
+        if let (Some(_), Some(retries)) = (&xfail, &retries) {
+            return Err(Error::new_spanned(
+                retries,
+                "`retries` cannot be used with `xfail`",
+            ));
+        }
+
         Ok(Self {
             crate_path: crate_path.unwrap_or_else(|| syn::parse_quote!(::rye)),
+            globals,
+            isolate,
+            retries,
+            resources,
+            skip_conditions,
+            xfail,
         })
     }
 }
 
+fn parse_resource_name(input: ParseStream<'_>) -> Result<LitStr> {
+    let name: LitStr = input.parse()?;
+    if name.value().is_empty() {
+        return Err(Error::new_spanned(
+            name,
+            "the resource name must not be empty",
+        ));
+    }
+    Ok(name)
+}
+
+struct FixtureParam {
+    path: Path,
+    mutable: bool,
+}
+
+fn extract_fixtures(item: &mut ItemFn) -> Result<Vec<FixtureParam>> {
+    let mut fixtures = vec![];
+    let mut errors = Errors::default();
+    for input in item.sig.inputs.iter_mut().skip(1) {
+        errors.append_if_error(extract_fixture(input).map(|fixture| fixtures.push(fixture)));
+    }
+    errors.into_result()?;
+    Ok(fixtures)
+}
+
+fn extract_fixture(input: &mut FnArg) -> Result<FixtureParam> {
+    let input = match input {
+        FnArg::Typed(input) => input,
+        FnArg::Receiver(receiver) => {
+            return Err(Error::new_spanned(
+                receiver,
+                "test functions cannot take the receiver",
+            ))
+        }
+    };
+
+    let mut path = None;
+    let mut parse_attr = |input: ParseStream<'_>| -> Result<()> {
+        match input.call(Ident::parse_any)? {
+            id if id == "fixture" => {
+                let _: Token![=] = input.parse()?;
+                path.replace(input.parse::<Path>()?);
+                Ok(())
+            }
+            id => Err(Error::new_spanned(id, "unknown parameter name")),
+        }
+    };
+
+    let mut errors = Errors::default();
+    input.attrs.retain(|attr| {
+        if !attr.path.is_ident("rye") {
+            return true;
+        }
+        errors.append_if_error(attr.parse_args_with(&mut parse_attr));
+        false
+    });
+    errors.into_result()?;
+
+    let mutable = match *input.ty {
+        Type::Reference(ref ty) => ty.mutability.is_some(),
+        ref ty => {
+            return Err(Error::new_spanned(
+                ty,
+                "fixtures must be received by reference, `&T` or `&mut T`",
+            ))
+        }
+    };
+
+    let path = match (path, &*input.pat) {
+        (Some(path), _) => path,
+        (None, Pat::Ident(pat)) => Path::from(pat.ident.clone()),
+        (None, pat) => {
+            return Err(Error::new_spanned(
+                pat,
+                "the fixture must be specified by `#[rye(fixture = path)]`",
+            ))
+        }
+    };
+
+    Ok(FixtureParam { path, mutable })
+}
+
 #[derive(Default)]
-struct Errors(Option<Error>);
+pub(crate) struct Errors(Option<Error>);
 
 impl Errors {
-    fn append_if_error(&mut self, res: Result<()>) {
+    pub(crate) fn append_if_error(&mut self, res: Result<()>) {
         match (self.0.as_mut(), res) {
             (Some(errors), Err(error)) => errors.combine(error),
             (None, Err(error)) => self.0 = Some(error),
@@ -146,7 +334,7 @@ impl Errors {
         }
     }
 
-    fn into_result(self) -> Result<()> {
+    pub(crate) fn into_result(self) -> Result<()> {
         match self.0 {
             None => Ok(()),
             Some(err) => Err(err),
@@ -158,6 +346,8 @@ type SectionId = u64;
 
 struct Section {
     id: SectionId,
+    name: Expr,
+    span: Span,
     ancestors: Vec<SectionId>,
     children: Vec<SectionId>,
 }
@@ -168,9 +358,13 @@ fn expand_builtins(item: &mut ItemFn) -> Vec<Section> {
         next_section_id: 0,
         parent: None,
         forbidden_sections: false,
+        in_loop: false,
+        in_body: false,
+        stmt_level: false,
+        run_in_place: false,
         block_state: BlockState::Setup,
     };
-    expand.visit_block_mut(&mut *item.block);
+    expand.visit_body_mut(&mut item.block);
     expand.sections.into_iter().map(|(_k, v)| v).collect()
 }
 
@@ -179,6 +373,15 @@ struct ExpandBuiltins {
     next_section_id: SectionId,
     parent: Option<SectionId>,
     forbidden_sections: bool,
+    in_loop: bool,
+    /// Whether the next block is the body of the test, a section or an
+    /// async block or closure run in place.
+    in_body: bool,
+    /// Whether the next expression is at the statement level of a body.
+    stmt_level: bool,
+    /// Whether the next expression is a closure or async block run in place,
+    /// that is, called or awaited as soon as it is created.
+    run_in_place: bool,
     block_state: BlockState,
 }
 
@@ -233,6 +436,8 @@ impl ExpandBuiltins {
             section_id,
             Section {
                 id: section_id,
+                name: name.clone(),
+                span: mac.span(),
                 ancestors,
                 children: vec![],
             },
@@ -240,7 +445,7 @@ impl ExpandBuiltins {
         self.next_section_id += 1;
 
         self.enter_section(section_id, |me| {
-            me.visit_block_mut(&mut *block);
+            me.visit_body_mut(&mut block);
         });
 
         Ok(Stmt::Item(Item::Verbatim(quote_spanned! { mac.span() =>
@@ -253,6 +458,11 @@ impl ExpandBuiltins {
             .unwrap_or_else(|err| Stmt::Item(Item::Verbatim(err.to_compile_error())))
     }
 
+    fn visit_body_mut(&mut self, block: &mut Block) {
+        self.in_body = true;
+        self.visit_block_mut(block);
+    }
+
     fn enter_section<F, R>(&mut self, section_id: SectionId, f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
@@ -273,6 +483,26 @@ impl ExpandBuiltins {
         res
     }
 
+    fn allow_sections<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = mem::replace(&mut self.forbidden_sections, false);
+        let res = f(self);
+        self.forbidden_sections = prev;
+        res
+    }
+
+    fn enter_loop<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = mem::replace(&mut self.in_loop, true);
+        let res = f(self);
+        self.in_loop = prev;
+        res
+    }
+
     fn with_block_state<F, R>(&mut self, state: BlockState, f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
@@ -286,12 +516,28 @@ impl ExpandBuiltins {
 
 impl VisitMut for ExpandBuiltins {
     fn visit_block_mut(&mut self, block: &mut Block) {
+        let in_body = mem::replace(&mut self.in_body, false);
         self.with_block_state(BlockState::Setup, |me| {
-            visit_mut::visit_block_mut(me, block);
+            for i in 0..block.stmts.len() {
+                let (stmt, rest) = block.stmts[i..].split_first_mut().unwrap();
+                me.stmt_level = in_body;
+                me.run_in_place = in_body && is_called_closure(stmt, rest);
+                me.visit_stmt_mut(stmt);
+            }
         });
+        self.stmt_level = false;
+        self.run_in_place = false;
     }
 
     fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        if let Some(section) = section_in_dynamic_section(stmt) {
+            *stmt = Stmt::Item(Item::Verbatim(
+                Error::new_spanned(section, "section cannot be described in dynamic_section")
+                    .to_compile_error(),
+            ));
+            return;
+        }
+
         match stmt {
             #[rustfmt::skip]
             | Stmt::Expr(Expr::Macro(ExprMacro { attrs, mac, .. }))
@@ -315,6 +561,237 @@ impl VisitMut for ExpandBuiltins {
             }
         }
     }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        let stmt_level = mem::replace(&mut self.stmt_level, false);
+        let run_in_place = mem::replace(&mut self.run_in_place, false);
+        match expr {
+            // The sections in async blocks and closures are enabled as usual only
+            // if they are run in place at the statement level of the test body.
+            // They could be run many times when created in loops, or not at all
+            // in the branches of `if` and `match`.
+            Expr::Async(ExprAsync { block, .. }) if stmt_level && run_in_place && !self.in_loop => {
+                self.allow_sections(|me| {
+                    me.visit_body_mut(block);
+                });
+            }
+            Expr::Closure(ExprClosure { body, .. })
+                if stmt_level && run_in_place && !self.in_loop =>
+            {
+                self.allow_sections(|me| match &mut **body {
+                    Expr::Block(ExprBlock { block, .. }) => me.visit_body_mut(block),
+                    body => me.visit_expr_mut(body),
+                });
+            }
+            Expr::Async(..) | Expr::Closure(..) => {
+                self.forbid_sections(|me| {
+                    visit_mut::visit_expr_mut(me, expr);
+                });
+            }
+            Expr::Await(ExprAwait { base, .. }) => {
+                self.stmt_level = stmt_level;
+                self.run_in_place = true;
+                self.visit_expr_mut(base);
+            }
+            Expr::Call(ExprCall { func, args, .. }) => {
+                self.stmt_level = stmt_level;
+                self.run_in_place = true;
+                self.visit_expr_mut(func);
+                for arg in args {
+                    self.visit_expr_mut(arg);
+                }
+            }
+            Expr::Paren(ExprParen { expr, .. }) => {
+                self.stmt_level = stmt_level;
+                self.run_in_place = run_in_place;
+                self.visit_expr_mut(expr);
+            }
+            Expr::Loop(..) | Expr::ForLoop(..) | Expr::While(..) => {
+                self.enter_loop(|me| {
+                    visit_mut::visit_expr_mut(me, expr);
+                });
+            }
+            expr => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+}
+
+/// Determine whether the statement binds a closure to a local variable that is
+/// only called directly in the rest of the block, `rest`.
+fn is_called_closure(stmt: &Stmt, rest: &[Stmt]) -> bool {
+    let name = match stmt {
+        Stmt::Local(Local {
+            pat: Pat::Ident(PatIdent { ident, .. }),
+            init: Some((_, init)),
+            ..
+        }) => match **init {
+            Expr::Closure(..) => ident,
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    let mut uses = ClosureUses {
+        name,
+        calls: 0,
+        nested: false,
+        invalid: false,
+    };
+    for stmt in rest {
+        uses.visit_stmt(stmt);
+    }
+    uses.calls > 0 && !uses.invalid
+}
+
+/// Collect the uses of a closure bound to the local variable `name`.
+///
+/// The closure must not be called in the nested blocks, loops, closures or
+/// conditional branches, nor be used as a value, since the sections in it are assumed to be run
+/// in the same scope where it is defined.
+struct ClosureUses<'a> {
+    name: &'a Ident,
+    calls: usize,
+    nested: bool,
+    invalid: bool,
+}
+
+impl ClosureUses<'_> {
+    fn nested<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let prev = mem::replace(&mut self.nested, true);
+        f(self);
+        self.nested = prev;
+    }
+}
+
+impl<'ast> Visit<'ast> for ClosureUses<'_> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.nested(|me| visit::visit_block(me, block));
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast ExprClosure) {
+        self.nested(|me| visit::visit_expr_closure(me, closure));
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast ExprWhile) {
+        self.nested(|me| visit::visit_expr_while(me, expr));
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast ExprForLoop) {
+        self.nested(|me| visit::visit_expr_for_loop(me, expr));
+    }
+
+    fn visit_expr_if(&mut self, expr: &'ast ExprIf) {
+        self.nested(|me| visit::visit_expr_if(me, expr));
+    }
+
+    fn visit_expr_match(&mut self, expr: &'ast ExprMatch) {
+        self.nested(|me| visit::visit_expr_match(me, expr));
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast ExprBinary) {
+        match expr.op {
+            // the right operand of `&&` and `||` may not be evaluated.
+            BinOp::And(..) | BinOp::Or(..) => {
+                self.visit_expr(&expr.left);
+                self.nested(|me| me.visit_expr(&expr.right));
+            }
+            _ => visit::visit_expr_binary(self, expr),
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        match *call.func {
+            Expr::Path(ref func) if func.path.is_ident(self.name) => {
+                if self.nested {
+                    self.invalid = true;
+                } else {
+                    self.calls += 1;
+                }
+                for arg in &call.args {
+                    self.visit_expr(arg);
+                }
+            }
+            _ => visit::visit_expr_call(self, call),
+        }
+    }
+
+    fn visit_expr_path(&mut self, path: &'ast ExprPath) {
+        if path.path.is_ident(self.name) {
+            self.invalid = true;
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        // The tokens in macros, including `section!`, cannot be inspected.
+        if contains_ident(mac.tokens.clone(), self.name) {
+            self.invalid = true;
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast Item) {
+        // the inner items cannot capture the local variables.
+    }
+}
+
+fn contains_ident(tokens: TokenStream, ident: &Ident) -> bool {
+    let mut prev = None;
+    for token in tokens {
+        match token {
+            // skip the fields, methods and the items in paths.
+            TokenTree::Ident(ref i) if i == ident && prev != Some('.') && prev != Some(':') => {
+                return true;
+            }
+            TokenTree::Group(ref group) if contains_ident(group.stream(), ident) => return true,
+            _ => (),
+        }
+        prev = match token {
+            TokenTree::Punct(punct) => Some(punct.as_char()),
+            _ => None,
+        };
+    }
+    false
+}
+
+/// Find `section!` in the body of `dynamic_section!`, which cannot be expanded
+/// since the tokens of macros are not visited.
+fn section_in_dynamic_section(stmt: &Stmt) -> Option<TokenStream> {
+    match stmt {
+        #[rustfmt::skip]
+        | Stmt::Expr(Expr::Macro(ExprMacro { mac, .. }))
+        | Stmt::Semi(Expr::Macro(ExprMacro { mac, .. }), _)
+        | Stmt::Item(Item::Macro(ItemMacro { mac, .. })) => {
+            match mac.path.segments.last() {
+                Some(segment) if segment.ident == "dynamic_section" => {
+                    find_section_macro(mac.tokens.clone())
+                }
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+fn find_section_macro(tokens: TokenStream) -> Option<TokenStream> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, token) in tokens.iter().enumerate() {
+        match (token, tokens.get(i + 1), tokens.get(i + 2)) {
+            (TokenTree::Ident(ident), Some(TokenTree::Punct(bang)), Some(body))
+                if ident == "section" && bang.as_char() == '!' =>
+            {
+                return Some(quote!(#ident #bang #body));
+            }
+            (TokenTree::Group(group), ..) => {
+                if let Some(section) = find_section_macro(group.stream()) {
+                    return Some(section);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
 }
 
 struct Generated<'a> {
@@ -322,6 +799,7 @@ struct Generated<'a> {
     args: &'a Args,
     item: &'a ItemFn,
     sections: &'a [Section],
+    fixtures: &'a [FixtureParam],
 }
 
 impl ToTokens for Generated<'_> {
@@ -364,8 +842,107 @@ impl ToTokens for Generated<'_> {
             (None, ..) => Ident::new("blocking", Span::call_site()),
         };
 
+        let isolate = self.params.isolate;
+        let resources = self.params.resources.iter().map(|resource| match resource {
+            Resource::Serial => quote!(__rye::Resource::Serial),
+            Resource::Named(name) => quote!(__rye::Resource::Named(#name)),
+        });
+        let sections = self.sections.iter().map(|section| {
+            let id = section.id;
+            let name = &section.name;
+            let location = quote_spanned!(section.span => __rye::location!());
+            quote! {
+                __rye::Section {
+                    id: #id,
+                    name: #name,
+                    location: #location,
+                }
+            }
+        });
+        let skip_conditions = self.params.skip_conditions.iter().map(|cond| match cond {
+            SkipCondition::If(path) => {
+                // The path is printed as the reason of skip, without spaces between tokens.
+                let name = quote!(#path).to_string().replace(' ', "");
+                quote! {
+                    __rye::SkipCondition::If {
+                        name: #name,
+                        f: #path,
+                    }
+                }
+            }
+            SkipCondition::Env(var) => quote!(__rye::SkipCondition::Env(#var)),
+            SkipCondition::Cmd(cmd) => quote!(__rye::SkipCondition::Cmd(#cmd)),
+        });
+        let xfail = match self.params.xfail {
+            Some(ref reason) => quote!(__rye::Option::Some(#reason)),
+            None => quote!(__rye::Option::None),
+        };
+        let retries = match self.params.retries {
+            Some(ref n) => quote!(__rye::Option::Some(#n)),
+            None => quote!(__rye::Option::None),
+        };
+
         let test_case_id = quote::format_ident!("__TEST_CASE_{}", ident);
 
+        let globals = self.params.globals.iter().map(|ty| {
+            quote! {
+                __rye::GlobalKey {
+                    type_id: __rye::TypeId::of::<#ty>,
+                    type_name: __rye::type_name::<#ty>,
+                }
+            }
+        });
+
+        let fixtures = self.fixtures.iter().map(|fixture| {
+            let path = &fixture.path;
+            let mutable = fixture.mutable;
+            quote! {
+                __rye::FixtureRequest {
+                    desc: &<#path as __rye::Fixture>::DESC,
+                    mutable: #mutable,
+                }
+            }
+        });
+
+        // When some fixtures are requested, the test function is called through
+        // a wrapper function that borrows their values from the context.
+        let (test_fn, wrapper) = if self.fixtures.is_empty() {
+            (ident.clone(), None)
+        } else {
+            let test_fn = Ident::new("__rye_test_fn", Span::call_site());
+            let asyncness = &self.item.sig.asyncness;
+            let output = &self.item.sig.output;
+            let guards: Vec<_> = (0..self.fixtures.len())
+                .map(|i| quote::format_ident!("__rye_fixture_{}", i))
+                .collect();
+            let bindings = self.fixtures.iter().zip(&guards).map(|(fixture, guard)| {
+                let path = &fixture.path;
+                if fixture.mutable {
+                    quote!(let mut #guard = ctx.fixture::<#path>();)
+                } else {
+                    quote!(let #guard = ctx.fixture::<#path>();)
+                }
+            });
+            let args = self.fixtures.iter().zip(&guards).map(|(fixture, guard)| {
+                if fixture.mutable {
+                    quote!(&mut *#guard)
+                } else {
+                    quote!(&*#guard)
+                }
+            });
+            let call = match asyncness {
+                Some(..) => quote!(#ident(ctx, #(#args),*).await),
+                None => quote!(#ident(ctx, #(#args),*)),
+            };
+            let wrapper = quote! {
+                #asyncness fn #test_fn(ctx: &mut __rye::Context<'_>) #output {
+                    #(#bindings)*
+                    #call
+                }
+            };
+            (test_fn, Some(wrapper))
+        };
+
         tokens.append_all(Some(quote! {
             #[allow(non_upper_case_globals)]
             const #ident: & #crate_path::_test_reexports::TestCase = {
@@ -374,12 +951,22 @@ impl ToTokens for Generated<'_> {
 
                 #item
 
+                #wrapper
+
                 &__rye::TestCase {
                     desc: __rye::TestDesc {
                         name: __rye::test_name!(#ident),
                         location: #location,
+                        globals: &[ #(#globals,)* ],
+                        fixtures: &[ #(#fixtures,)* ],
+                        isolate: #isolate,
+                        retries: #retries,
+                        resources: &[ #(#resources),* ],
+                        skip_conditions: &[ #(#skip_conditions,)* ],
+                        xfail: #xfail,
+                        sections: &[ #(#sections,)* ],
                     },
-                    testfn: __rye::test_fn!(@#test_fn_id #ident),
+                    testfn: __rye::test_fn!(@#test_fn_id #test_fn),
                     plans: &[ #(#plans,)* ],
                 }
             };
@@ -466,4 +1053,44 @@ mod tests {
     fn return_result() {
         test_expanded("09-return-result");
     }
+
+    #[test]
+    fn globals() {
+        test_expanded("10-globals");
+    }
+
+    #[test]
+    fn fixtures() {
+        test_expanded("11-fixtures");
+    }
+
+    #[test]
+    fn isolate() {
+        test_expanded("12-isolate");
+    }
+
+    #[test]
+    fn retries() {
+        test_expanded("13-retries");
+    }
+
+    #[test]
+    fn resources() {
+        test_expanded("14-resources");
+    }
+
+    #[test]
+    fn skip_conditions() {
+        test_expanded("15-skip-conditions");
+    }
+
+    #[test]
+    fn xfail() {
+        test_expanded("16-xfail");
+    }
+
+    #[test]
+    fn closures() {
+        test_expanded("17-closures");
+    }
 }
@@ -0,0 +1,3 @@
+fn numbers() -> Vec<i32> {
+    vec![1, 2, 3]
+}
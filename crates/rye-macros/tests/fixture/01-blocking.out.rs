@@ -0,0 +1,27 @@
+fn numbers() -> Vec<i32> {
+    vec![1, 2, 3]
+}
+
+#[allow(non_camel_case_types, dead_code)]
+struct numbers {}
+
+const _: () = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn __rye_setup() -> __rye::FixtureValue {
+        __rye::FixtureValue::owned(numbers())
+    }
+
+    impl __rye::Fixture for numbers {
+        type Output = Vec<i32>;
+
+        const DESC: __rye::FixtureDesc = __rye::FixtureDesc {
+            name: __rye::stringify!(numbers),
+            type_id: __rye::TypeId::of::<numbers>,
+            scope: __rye::FixtureScope::Section,
+            setup: __rye::FixtureSetup::Blocking(__rye_setup),
+            teardown: __rye::Option::None,
+        };
+    }
+};
@@ -0,0 +1,5 @@
+#[rye(scope = test)]
+#[rye(teardown = close_connection)]
+pub(crate) async fn connection() -> Connection {
+    Connection::open().await
+}
@@ -0,0 +1,39 @@
+pub(crate) async fn connection() -> Connection {
+    Connection::open().await
+}
+
+#[allow(non_camel_case_types, dead_code)]
+pub(crate) struct connection {}
+
+const _: () = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn __rye_setup() -> __rye::BoxFuture<'static, __rye::FixtureValue> {
+        __rye::Box::pin(async {
+            __rye::FixtureValue::owned(connection().await)
+        })
+    }
+
+    fn __rye_teardown(
+        value: __rye::FixtureValue,
+    ) -> __rye::BoxFuture<'static, ()> {
+        __rye::Box::pin(async move {
+            if let __rye::Option::Some(value) = value.into_owned::<Connection>() {
+                close_connection(value).await;
+            }
+        })
+    }
+
+    impl __rye::Fixture for connection {
+        type Output = Connection;
+
+        const DESC: __rye::FixtureDesc = __rye::FixtureDesc {
+            name: __rye::stringify!(connection),
+            type_id: __rye::TypeId::of::<connection>,
+            scope: __rye::FixtureScope::Test,
+            setup: __rye::FixtureSetup::Async(__rye_setup),
+            teardown: __rye::Option::Some(__rye_teardown),
+        };
+    }
+};
@@ -0,0 +1,5 @@
+#[rye(scope = session)]
+#[rye(crate = path::to::rye)]
+fn config() -> Config {
+    Config::load()
+}
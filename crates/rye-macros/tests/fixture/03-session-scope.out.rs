@@ -0,0 +1,27 @@
+fn config() -> Config {
+    Config::load()
+}
+
+#[allow(non_camel_case_types, dead_code)]
+struct config {}
+
+const _: () = {
+    #[allow(unused_imports)]
+    use path::to::rye::_test_reexports as __rye;
+
+    fn __rye_setup() -> __rye::FixtureValue {
+        __rye::FixtureValue::shared(config())
+    }
+
+    impl __rye::Fixture for config {
+        type Output = Config;
+
+        const DESC: __rye::FixtureDesc = __rye::FixtureDesc {
+            name: __rye::stringify!(config),
+            type_id: __rye::TypeId::of::<config>,
+            scope: __rye::FixtureScope::Session,
+            setup: __rye::FixtureSetup::Blocking(__rye_setup),
+            teardown: __rye::Option::None,
+        };
+    }
+};
@@ -25,6 +25,17 @@ const case_sync_nested: & ::rye::_test_reexports::TestCase = {
         desc: __rye::TestDesc {
             name: __rye::test_name!(case_sync_nested),
             location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[
+                __rye::Section { id: 0u64, name: "resizing bigger changes size and capacity", location: __rye::location!(), },
+                __rye::Section { id: 1u64, name: "shrinking smaller does not changes capacity", location: __rye::location!(), },
+            ],
         },
         testfn: __rye::test_fn!(@blocking case_sync_nested),
         plans: &[
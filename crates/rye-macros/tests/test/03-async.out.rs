@@ -19,6 +19,16 @@ const case_async: & ::rye::_test_reexports::TestCase = {
         desc: __rye::TestDesc {
             name: __rye::test_name!(case_async),
             location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[
+                __rye::Section { id: 0u64, name: "resizing bigger changes size and capacity", location: __rye::location!(), },
+            ],
         },
         testfn: __rye::test_fn!(@async case_async),
         plans: &[
@@ -35,6 +35,21 @@ const multi_section_in_scope: & ::rye::_test_reexports::TestCase = {
         desc: __rye::TestDesc {
             name: __rye::test_name!(multi_section_in_scope),
             location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[
+                __rye::Section { id: 0u64, name: "section1", location: __rye::location!(), },
+                __rye::Section { id: 1u64, name: "section2", location: __rye::location!(), },
+                __rye::Section { id: 2u64, name: "section2-1", location: __rye::location!(), },
+                __rye::Section { id: 3u64, name: "section2-1-2", location: __rye::location!(), },
+                __rye::Section { id: 4u64, name: "section2-2", location: __rye::location!(), },
+                __rye::Section { id: 5u64, name: "section3", location: __rye::location!(), },
+            ],
         },
         testfn: __rye::test_fn!(@blocking multi_section_in_scope),
         plans: &[
@@ -15,6 +15,14 @@ const ignore_inner_items: & ::rye::_test_reexports::TestCase = {
         desc: __rye::TestDesc {
             name: __rye::test_name!(ignore_inner_items),
             location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
         },
         testfn: __rye::test_fn!(@blocking ignore_inner_items),
         plans: &[
@@ -24,6 +24,16 @@ const attributes: & path::to::rye::_test_reexports::TestCase = {
         desc: __rye::TestDesc {
             name: __rye::test_name!(attributes),
             location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[
+                __rye::Section { id: 0u64, name: "with unused variable", location: __rye::location!(), },
+            ],
         },
         testfn: __rye::test_fn!(@blocking attributes),
         plans: &[
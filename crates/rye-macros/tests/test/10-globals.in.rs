@@ -0,0 +1,5 @@
+#[rye(global = Database)]
+#[rye(global = crate::Config)]
+fn with_globals(ctx: &mut Context<'_>) {
+    let _db = ctx.global::<Database>();
+}
@@ -0,0 +1,42 @@
+#[allow(non_upper_case_globals)]
+const with_globals: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn with_globals(ctx: &mut Context<'_>) {
+        let _db = ctx.global::<Database>();
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(with_globals),
+            location: __rye::location!(),
+            globals: &[
+                __rye::GlobalKey {
+                    type_id: __rye::TypeId::of::<Database>,
+                    type_name: __rye::type_name::<Database>,
+                },
+                __rye::GlobalKey {
+                    type_id: __rye::TypeId::of::<crate::Config>,
+                    type_name: __rye::type_name::<crate::Config>,
+                },
+            ],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@blocking with_globals),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_with_globals: & ::rye::_test_reexports::TestCase = with_globals;
+}
@@ -0,0 +1,8 @@
+async fn with_fixtures(
+    ctx: &mut Context<'_>,
+    db: &Database,
+    #[rye(fixture = crate::fixtures::temp_dir)] dir: &mut TempDir,
+) {
+    assert!(db.is_connected());
+    dir.create_file("foo.txt").await;
+}
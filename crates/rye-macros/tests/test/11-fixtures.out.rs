@@ -0,0 +1,53 @@
+#[allow(non_upper_case_globals)]
+const with_fixtures: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    async fn with_fixtures(
+        ctx: &mut Context<'_>,
+        db: &Database,
+        dir: &mut TempDir,
+    ) {
+        assert!(db.is_connected());
+        dir.create_file("foo.txt").await;
+    }
+
+    async fn __rye_test_fn(ctx: &mut __rye::Context<'_>) {
+        let __rye_fixture_0 = ctx.fixture::<db>();
+        let mut __rye_fixture_1 = ctx.fixture::<crate::fixtures::temp_dir>();
+        with_fixtures(ctx, &*__rye_fixture_0, &mut *__rye_fixture_1).await
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(with_fixtures),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[
+                __rye::FixtureRequest {
+                    desc: &<db as __rye::Fixture>::DESC,
+                    mutable: false,
+                },
+                __rye::FixtureRequest {
+                    desc: &<crate::fixtures::temp_dir as __rye::Fixture>::DESC,
+                    mutable: true,
+                },
+            ],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@async __rye_test_fn),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_with_fixtures: & ::rye::_test_reexports::TestCase = with_fixtures;
+}
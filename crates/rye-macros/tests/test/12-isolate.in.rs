@@ -0,0 +1,4 @@
+#[rye(isolate)]
+fn isolated(ctx: &mut Context<'_>) {
+    unsafe { call_ffi() };
+}
@@ -0,0 +1,33 @@
+#[allow(non_upper_case_globals)]
+const isolated: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn isolated(ctx: &mut Context<'_>) {
+        unsafe { call_ffi() };
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(isolated),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: true,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@blocking isolated),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_isolated: & ::rye::_test_reexports::TestCase = isolated;
+}
@@ -0,0 +1,4 @@
+#[rye(retries = 3)]
+async fn flaky(ctx: &mut Context<'_>) {
+    connect_with_timeout().await;
+}
@@ -0,0 +1,33 @@
+#[allow(non_upper_case_globals)]
+const flaky: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    async fn flaky(ctx: &mut Context<'_>) {
+        connect_with_timeout().await;
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(flaky),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::Some(3),
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@async flaky),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_flaky: & ::rye::_test_reexports::TestCase = flaky;
+}
@@ -0,0 +1,4 @@
+#[rye(serial)]
+#[rye(serial(group = "db"))]
+#[rye(resource = "port-8080")]
+fn exclusive(ctx: &mut Context<'_>) {}
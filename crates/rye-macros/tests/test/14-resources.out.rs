@@ -0,0 +1,31 @@
+#[allow(non_upper_case_globals)]
+const exclusive: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn exclusive(ctx: &mut Context<'_>) {}
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(exclusive),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[__rye::Resource::Serial, __rye::Resource::Named("db"), __rye::Resource::Named("port-8080")],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@blocking exclusive),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_exclusive: & ::rye::_test_reexports::TestCase = exclusive;
+}
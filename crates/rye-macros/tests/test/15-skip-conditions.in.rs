@@ -0,0 +1,6 @@
+#[rye(skip_if = platform::is_ci)]
+#[rye(require_env = "DATABASE_URL")]
+#[rye(require_cmd = "git")]
+fn external(ctx: &mut Context<'_>) {
+    do_something();
+}
@@ -0,0 +1,40 @@
+#[allow(non_upper_case_globals)]
+const external: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn external(ctx: &mut Context<'_>) {
+        do_something();
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(external),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[
+                __rye::SkipCondition::If {
+                    name: "platform::is_ci",
+                    f: platform::is_ci,
+                },
+                __rye::SkipCondition::Env("DATABASE_URL"),
+                __rye::SkipCondition::Cmd("git"),
+            ],
+            xfail: __rye::Option::None,
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@blocking external),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_external: & ::rye::_test_reexports::TestCase = external;
+}
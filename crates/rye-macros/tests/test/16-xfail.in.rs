@@ -0,0 +1,4 @@
+#[rye(xfail = "https://github.com/ubnt-intrepid/rye/issues/1")]
+fn known_bug(ctx: &mut Context<'_>) {
+    do_something();
+}
@@ -0,0 +1,33 @@
+#[allow(non_upper_case_globals)]
+const known_bug: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    fn known_bug(ctx: &mut Context<'_>) {
+        do_something();
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(known_bug),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::Some("https://github.com/ubnt-intrepid/rye/issues/1"),
+            sections: &[],
+        },
+        testfn: __rye::test_fn!(@blocking known_bug),
+        plans: &[
+            __rye::TestPlan { target: None, ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_known_bug: & ::rye::_test_reexports::TestCase = known_bug;
+}
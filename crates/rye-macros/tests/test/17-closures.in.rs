@@ -0,0 +1,15 @@
+async fn case_closures(ctx: &mut Context<'_>) {
+    async {
+        section!(ctx, "section in async block", {
+            do_something().await;
+        });
+    }
+    .await;
+
+    let mut run = |ctx: &mut Context<'_>| {
+        section!(ctx, "section in closure", {
+            do_something_else();
+        });
+    };
+    run(ctx);
+}
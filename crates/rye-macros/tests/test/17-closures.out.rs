@@ -0,0 +1,49 @@
+#[allow(non_upper_case_globals)]
+const case_closures: & ::rye::_test_reexports::TestCase = {
+    #[allow(unused_imports)]
+    use ::rye::_test_reexports as __rye;
+
+    async fn case_closures(ctx: &mut Context<'_>) {
+        async {
+            __rye::section!(ctx, 0u64, "section in async block", {
+                do_something().await;
+            });
+        }
+        .await;
+
+        let mut run = |ctx: &mut Context<'_>| {
+            __rye::section!(ctx, 1u64, "section in closure", {
+                do_something_else();
+            });
+        };
+        run(ctx);
+    }
+
+    &__rye::TestCase {
+        desc: __rye::TestDesc {
+            name: __rye::test_name!(case_closures),
+            location: __rye::location!(),
+            globals: &[],
+            fixtures: &[],
+            isolate: false,
+            retries: __rye::Option::None,
+            resources: &[],
+            skip_conditions: &[],
+            xfail: __rye::Option::None,
+            sections: &[
+                __rye::Section { id: 0u64, name: "section in async block", location: __rye::location!(), },
+                __rye::Section { id: 1u64, name: "section in closure", location: __rye::location!(), },
+            ],
+        },
+        testfn: __rye::test_fn!(@async case_closures),
+        plans: &[
+            __rye::TestPlan { target: Some(0u64), ancestors: &[], },
+            __rye::TestPlan { target: Some(1u64), ancestors: &[], },
+        ],
+    }
+};
+
+::rye::__test_case! {
+    #[allow(non_upper_case_globals)]
+    static __TEST_CASE_case_closures: & ::rye::_test_reexports::TestCase = case_closures;
+}
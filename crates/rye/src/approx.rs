@@ -0,0 +1,448 @@
+//! Approximate comparison of floating point values, like `Approx` in Catch2.
+
+use std::fmt;
+
+/// Create a value compared approximately with the floating point values.
+///
+/// The comparison succeeds if the difference between both values is within
+/// `margin`, or within `epsilon` relative to the magnitude of the expected value.
+/// The slices, arrays and vectors are compared element-wise.
+///
+/// ```
+/// use rye::approx;
+///
+/// assert!(0.1 + 0.2 == approx(0.3));
+/// assert!(approx(100.0).epsilon(0.01) == 100.5);
+/// assert!(approx(0.0).margin(1e-6) == 1e-7);
+/// assert!(approx([1.0, 2.0]) == vec![1.0, 2.0 + 1e-9]);
+///
+/// let err = approx(1.0).check(&1.1).unwrap_err();
+/// assert!(err.to_string().contains("difference: 1.000e-1"));
+/// ```
+pub fn approx<T>(expected: T) -> Approx<T>
+where
+    T: ApproxValue,
+{
+    Approx {
+        expected,
+        epsilon: f64::from(f32::EPSILON) * 100.0,
+        margin: 0.0,
+    }
+}
+
+/// A floating point value or a sequence of them, compared approximately.
+///
+/// This value is created by [`approx`].
+#[derive(Clone, Copy)]
+pub struct Approx<T> {
+    expected: T,
+    epsilon: f64,
+    margin: f64,
+}
+
+impl<T> Approx<T>
+where
+    T: ApproxValue,
+{
+    /// Set the allowed difference relative to the magnitude of expected value.
+    ///
+    /// The default value is `f32::EPSILON * 100`.
+    pub fn epsilon(self, epsilon: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&epsilon),
+            "epsilon must be in the range of [0, 1] (was {})",
+            epsilon
+        );
+        Self { epsilon, ..self }
+    }
+
+    /// Set the allowed absolute difference.
+    ///
+    /// The default value is zero.
+    pub fn margin(self, margin: f64) -> Self {
+        assert!(
+            margin >= 0.0,
+            "margin must be non-negative (was {})",
+            margin
+        );
+        Self { margin, ..self }
+    }
+
+    /// Compare the actual value with the expected one, and return the details
+    /// of the first mismatch if they are not approximately equal.
+    pub fn check<U>(&self, actual: &U) -> Result<(), ApproxError>
+    where
+        U: ApproxValue + ?Sized,
+    {
+        let expected = self.expected.to_f64s();
+        let actual = actual.to_f64s();
+        if expected.len() != actual.len() {
+            return Err(ApproxError::Length {
+                expected: expected.len(),
+                actual: actual.len(),
+            });
+        }
+
+        let is_scalar = self.expected.is_scalar();
+        for (i, (&expected, &actual)) in expected.iter().zip(&actual).enumerate() {
+            let tolerance = self.tolerance(expected);
+            #[allow(clippy::float_cmp)]
+            let matched = expected == actual || (expected - actual).abs() <= tolerance;
+            if !matched {
+                return Err(ApproxError::Value {
+                    index: if is_scalar { None } else { Some(i) },
+                    expected,
+                    actual,
+                    tolerance,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerance(&self, expected: f64) -> f64 {
+        let magnitude = if expected.is_infinite() {
+            0.0
+        } else {
+            expected.abs()
+        };
+        self.margin.max(self.epsilon * magnitude)
+    }
+}
+
+impl<T> fmt::Display for Approx<T>
+where
+    T: ApproxValue,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = self.expected.to_f64s();
+        if self.expected.is_scalar() {
+            write!(f, "approx({:?})", expected[0])
+        } else {
+            write!(f, "approx({:?})", expected)
+        }
+    }
+}
+
+impl<T> fmt::Debug for Approx<T>
+where
+    T: ApproxValue,
+{
+    #[allow(clippy::float_cmp)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)?;
+        if self.epsilon != approx(0.0).epsilon {
+            write!(f, ".epsilon({:?})", self.epsilon)?;
+        }
+        if self.margin != 0.0 {
+            write!(f, ".margin({:?})", self.margin)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, U> PartialEq<U> for Approx<T>
+where
+    T: ApproxValue,
+    U: ApproxValue,
+{
+    fn eq(&self, other: &U) -> bool {
+        self.check(other).is_ok()
+    }
+}
+
+macro_rules! impl_reversed_eq {
+    ($( [$($generics:tt)*] $t:ty, )*) => {$(
+        impl<$($generics)* U> PartialEq<Approx<U>> for $t
+        where
+            U: ApproxValue,
+        {
+            fn eq(&self, other: &Approx<U>) -> bool {
+                other.check(self).is_ok()
+            }
+        }
+    )*};
+}
+
+impl_reversed_eq! {
+    [] f32,
+    [] f64,
+    [T: Float,] Vec<T>,
+    [T: Float,] [T],
+}
+
+/// The mismatch found in the approximate comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApproxError {
+    /// The number of elements is different.
+    Length {
+        /// The number of expected elements.
+        expected: usize,
+        /// The number of actual elements.
+        actual: usize,
+    },
+    /// The values are not approximately equal.
+    Value {
+        /// The index of the mismatched element, if the values are sequences.
+        index: Option<usize>,
+        /// The expected value.
+        expected: f64,
+        /// The actual value.
+        actual: f64,
+        /// The allowed difference computed from `epsilon` and `margin`.
+        tolerance: f64,
+    },
+}
+
+impl fmt::Display for ApproxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ApproxError::Length { expected, actual } => write!(
+                f,
+                "the number of elements is different: expected {}, actual {}",
+                expected, actual
+            ),
+            ApproxError::Value {
+                index,
+                expected,
+                actual,
+                tolerance,
+            } => {
+                if let Some(index) = index {
+                    write!(f, "at index {}: ", index)?;
+                }
+                write!(
+                    f,
+                    "{:?} is not approximately equal to {:?} (difference: {:.3e}, tolerance: {:.3e})",
+                    actual,
+                    expected,
+                    (actual - expected).abs(),
+                    tolerance
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApproxError {}
+
+/// Floating point types that can be compared approximately.
+pub trait Float: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    fn to_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+impl Float for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Values that can be compared approximately: floating point numbers and
+/// the slices, arrays and vectors of them.
+pub trait ApproxValue: sealed::Sealed {
+    #[doc(hidden)]
+    fn to_f64s(&self) -> Vec<f64>;
+
+    #[doc(hidden)]
+    fn is_scalar(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! impl_for_floats {
+    ($($t:ty)*) => {$(
+        impl ApproxValue for $t {
+            fn to_f64s(&self) -> Vec<f64> {
+                vec![self.to_f64()]
+            }
+
+            fn is_scalar(&self) -> bool {
+                true
+            }
+        }
+    )*};
+}
+
+impl_for_floats!(f32 f64);
+
+impl<T: Float> ApproxValue for [T] {
+    fn to_f64s(&self) -> Vec<f64> {
+        self.iter().map(|x| x.to_f64()).collect()
+    }
+}
+
+impl<T: Float> ApproxValue for Vec<T> {
+    fn to_f64s(&self) -> Vec<f64> {
+        self[..].to_f64s()
+    }
+}
+
+impl<T: ApproxValue + ?Sized> ApproxValue for &T {
+    fn to_f64s(&self) -> Vec<f64> {
+        (**self).to_f64s()
+    }
+
+    fn is_scalar(&self) -> bool {
+        (**self).is_scalar()
+    }
+}
+
+macro_rules! impl_for_arrays {
+    ($($n:expr)*) => {$(
+        impl<T: Float> ApproxValue for [T; $n] {
+            fn to_f64s(&self) -> Vec<f64> {
+                self[..].to_f64s()
+            }
+        }
+
+        impl<T: Float> sealed::Sealed for [T; $n] {}
+
+        impl_reversed_eq!([T: Float,] [T; $n],);
+    )*};
+}
+
+impl_for_arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+// ==== require_eq! ====
+
+/// The pair of values compared by `require_eq!`.
+///
+/// If either of them is `Approx`, the mismatch found by `Approx::check` is
+/// reported along with the values by `ApproxMismatch`, and otherwise nothing
+/// is reported by `NoApproxMismatch`.
+#[doc(hidden)]
+pub struct ApproxPair<'a, L: ?Sized, R: ?Sized>(pub &'a L, pub &'a R);
+
+#[doc(hidden)]
+pub trait ApproxMismatch {
+    fn approx_mismatch(&self) -> Option<ApproxError>;
+}
+
+impl<T, U> ApproxMismatch for ApproxPair<'_, Approx<T>, U>
+where
+    T: ApproxValue,
+    U: ApproxValue + ?Sized,
+{
+    fn approx_mismatch(&self) -> Option<ApproxError> {
+        self.0.check(self.1).err()
+    }
+}
+
+impl<T, U> ApproxMismatch for ApproxPair<'_, U, Approx<T>>
+where
+    T: ApproxValue,
+    U: ApproxValue + ?Sized,
+{
+    fn approx_mismatch(&self) -> Option<ApproxError> {
+        self.1.check(self.0).err()
+    }
+}
+
+#[doc(hidden)]
+pub trait NoApproxMismatch {
+    fn approx_mismatch(&self) -> Option<ApproxError> {
+        None
+    }
+}
+
+impl<L: ?Sized, R: ?Sized> NoApproxMismatch for &ApproxPair<'_, L, R> {}
+
+mod sealed {
+    use super::Float;
+
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+    impl<T: Float> Sealed for [T] {}
+    impl<T: Float> Sealed for Vec<T> {}
+    impl<T: super::ApproxValue + ?Sized> Sealed for &T {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalar() {
+        assert!(approx(0.3) == 0.1 + 0.2);
+        assert!(0.1_f32 + 0.2 == approx(0.3_f32));
+        assert!(approx(1.0) != 1.1);
+        assert!(approx(100.0).epsilon(0.01) == 101.0);
+        assert!(approx(100.0).epsilon(0.01) != 101.5);
+        assert!(approx(0.0) != 1e-10);
+        assert!(approx(0.0).margin(1e-9) == 1e-10);
+        assert!(approx(std::f64::INFINITY) == std::f64::INFINITY);
+        assert!(approx(std::f64::NAN) != std::f64::NAN);
+    }
+
+    #[test]
+    fn sequences() {
+        assert!(approx([1.0, 2.0]) == [1.0, 2.0 + 1e-12]);
+        assert!(approx(vec![1.0_f32]) == &[1.0_f32][..]);
+        assert!(vec![1.0, 2.0] == approx([1.0, 2.0]));
+        assert!([1.0, 2.0] == approx([1.0, 2.0 + 1e-12]));
+        assert!([1.0_f32; 3] == approx(vec![1.0_f32; 3]));
+        assert!([1.0, 2.0] != approx([1.0, 2.5]));
+        assert!(approx([1.0, 2.0]) != [1.0, 2.0, 3.0]);
+        assert_eq!(
+            approx([1.0, 2.0]).check(&[1.0, 2.5]),
+            Err(ApproxError::Value {
+                index: Some(1),
+                expected: 2.0,
+                actual: 2.5,
+                tolerance: 2.0 * f64::from(f32::EPSILON) * 100.0,
+            })
+        );
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(approx(1.5).to_string(), "approx(1.5)");
+        assert_eq!(format!("{:?}", approx(1.5)), "approx(1.5)");
+        assert_eq!(
+            format!("{:?}", approx([1.0, 2.0]).epsilon(0.01).margin(0.5)),
+            "approx([1.0, 2.0]).epsilon(0.01).margin(0.5)"
+        );
+        assert_eq!(approx([1.0, 2.0]).to_string(), "approx([1.0, 2.0])");
+        assert_eq!(
+            approx(1.0)
+                .margin(0.05)
+                .check(&1.1)
+                .unwrap_err()
+                .to_string(),
+            "1.1 is not approximately equal to 1.0 (difference: 1.000e-1, tolerance: 5.000e-2)"
+        );
+        assert_eq!(
+            approx([1.0]).check(&[1.0, 2.0]).unwrap_err().to_string(),
+            "the number of elements is different: expected 1, actual 2"
+        );
+    }
+
+    #[test]
+    fn mismatch_in_require_eq() {
+        assert_eq!(
+            ApproxPair(&1.1, &approx(1.0)).approx_mismatch(),
+            approx(1.0).check(&1.1).err()
+        );
+        assert_eq!(
+            ApproxPair(&approx([1.0]), &vec![1.0, 2.0]).approx_mismatch(),
+            Some(ApproxError::Length {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(ApproxPair(&0.3, &approx(0.1 + 0.2)).approx_mismatch(), None);
+        assert_eq!((&ApproxPair(&1.1, &1.0)).approx_mismatch(), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Helpers for `require_panics!` and `require_err!`.
+
+#![allow(missing_docs)]
+
+use crate::test::Abort;
+use futures_core::{
+    future::Future,
+    task::{self, Poll},
+};
+use pin_project::pin_project;
+use std::{
+    any::Any,
+    error::Error,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+};
+
+/// Call the function and return the panic message if it panicked.
+///
+/// The unwinding by `skip!` or `fail!` is propagated to the test runner.
+pub fn catch_panic<F, R>(f: F) -> Result<R, String>
+where
+    F: FnOnce() -> R,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(panic_message)
+}
+
+/// Run the future and return the panic message if it panicked.
+pub fn catch_panic_async<Fut>(fut: Fut) -> CatchPanic<Fut>
+where
+    Fut: Future,
+{
+    CatchPanic { fut }
+}
+
+#[pin_project]
+pub struct CatchPanic<Fut> {
+    #[pin]
+    fut: Fut,
+}
+
+impl<Fut> Future for CatchPanic<Fut>
+where
+    Fut: Future,
+{
+    type Output = Result<Fut::Output, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        let fut = me.fut;
+        match panic::catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(panic_message(payload))),
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send + 'static>) -> String {
+    if payload.is::<Abort>() {
+        panic::resume_unwind(payload);
+    }
+    if let Some(msg) = payload.downcast_ref::<&'static str>() {
+        (*msg).to_owned()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+/// The type-erased error values that can be downcast by `require_err!`.
+pub trait DowncastError {
+    fn is_type<T>(&self) -> bool
+    where
+        T: Error + Send + Sync + 'static;
+}
+
+impl DowncastError for anyhow::Error {
+    fn is_type<T>(&self) -> bool
+    where
+        T: Error + Send + Sync + 'static,
+    {
+        self.is::<T>()
+    }
+}
+
+macro_rules! impl_downcast_error {
+    ($($t:ty),*) => {$(
+        impl DowncastError for $t {
+            fn is_type<T>(&self) -> bool
+            where
+                T: Error + Send + Sync + 'static,
+            {
+                self.is::<T>()
+            }
+        }
+    )*};
+}
+
+impl_downcast_error! {
+    Box<dyn Error + 'static>,
+    Box<dyn Error + Send + 'static>,
+    Box<dyn Error + Send + Sync + 'static>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fmt, io};
+
+    #[test]
+    fn catch_panics() {
+        assert_eq!(catch_panic(|| 1), Ok(1));
+        assert_eq!(
+            catch_panic(|| panic!("explicit panic")),
+            Err::<(), _>("explicit panic".to_owned())
+        );
+        assert_eq!(
+            catch_panic(|| panic!("index {} is out of bounds", 3)),
+            Err::<(), _>("index 3 is out of bounds".to_owned())
+        );
+        assert_eq!(
+            futures_executor::block_on(catch_panic_async(async { panic!("async panic") })),
+            Err::<(), _>("async panic".to_owned())
+        );
+    }
+
+    #[test]
+    fn downcast_errors() {
+        #[derive(Debug)]
+        struct MyError;
+        impl fmt::Display for MyError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("my error")
+            }
+        }
+        impl Error for MyError {}
+
+        let err = anyhow::Error::from(MyError);
+        assert!(err.is_type::<MyError>());
+        assert!(!err.is_type::<io::Error>());
+
+        let err: Box<dyn Error + Send + Sync> = Box::new(MyError);
+        assert!(err.is_type::<MyError>());
+        assert!(!err.is_type::<io::Error>());
+    }
+}
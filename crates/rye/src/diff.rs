@@ -0,0 +1,337 @@
+#![allow(missing_docs)]
+
+use crate::approx::ApproxError;
+use std::{fmt, io};
+use termcolor::{Color, ColorSpec, WriteColor};
+
+/// The line-level difference between two texts, attached to the failures.
+///
+/// It is rendered in the unified format, or colored by `ConsoleReporter`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub(crate) old_label: String,
+    pub(crate) new_label: String,
+    pub(crate) old: String,
+    pub(crate) new: String,
+}
+
+impl Diff {
+    pub(crate) fn new(old_label: &str, new_label: &str, old: &str, new: &str) -> Self {
+        Self {
+            old_label: old_label.into(),
+            new_label: new_label.into(),
+            old: old.into(),
+            new: new.into(),
+        }
+    }
+
+    /// Write the diff colored, like `ConsoleReporter` colors the outcomes.
+    ///
+    /// The removed lines are written in red and the added lines in green, and
+    /// the changed words in each pair of them are emphasized additionally.
+    /// Nothing is colored if the stream does not support colors.
+    pub(crate) fn write_colored<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: WriteColor + ?Sized,
+    {
+        write_line(w, &format!("--- {}", self.old_label), Some(Color::Red))?;
+        writeln!(w)?;
+        write_line(w, &format!("+++ {}", self.new_label), Some(Color::Green))?;
+
+        let lines = diff::lines(&self.old, &self.new);
+        let mut i = 0;
+        while i < lines.len() {
+            if let diff::Result::Both(line, _) = lines[i] {
+                write!(w, "\n {}", line)?;
+                i += 1;
+                continue;
+            }
+
+            // Collect a hunk of removed lines followed by added lines, and pair them.
+            let mut removed = vec![];
+            while let Some(&diff::Result::Left(line)) = lines.get(i) {
+                removed.push(line);
+                i += 1;
+            }
+            let mut added = vec![];
+            while let Some(&diff::Result::Right(line)) = lines.get(i) {
+                added.push(line);
+                i += 1;
+            }
+            for (j, line) in removed.iter().enumerate() {
+                writeln!(w)?;
+                match added.get(j) {
+                    Some(pair) => write_words(w, line, pair, false)?,
+                    None => write_line(w, &format!("-{}", line), Some(Color::Red))?,
+                }
+            }
+            for (j, line) in added.iter().enumerate() {
+                writeln!(w)?;
+                match removed.get(j) {
+                    Some(pair) => write_words(w, pair, line, true)?,
+                    None => write_line(w, &format!("+{}", line), Some(Color::Green))?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--- {}\n+++ {}", self.old_label, self.new_label)?;
+        for line in diff::lines(&self.old, &self.new) {
+            match line {
+                diff::Result::Left(l) => write!(f, "\n-{}", l)?,
+                diff::Result::Right(r) => write!(f, "\n+{}", r)?,
+                diff::Result::Both(l, _) => write!(f, "\n {}", l)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_line<W>(w: &mut W, line: &str, color: Option<Color>) -> io::Result<()>
+where
+    W: WriteColor + ?Sized,
+{
+    w.set_color(ColorSpec::new().set_fg(color))?;
+    write!(w, "{}", line)?;
+    w.reset()
+}
+
+/// Write either of the removed or added line, emphasizing the changed words.
+fn write_words<W>(w: &mut W, removed: &str, added: &str, is_added: bool) -> io::Result<()>
+where
+    W: WriteColor + ?Sized,
+{
+    let color = if is_added { Color::Green } else { Color::Red };
+    let mut normal = ColorSpec::new();
+    normal.set_fg(Some(color));
+    let mut emphasized = normal.clone();
+    emphasized.set_bold(true).set_underline(true);
+
+    let old = words(removed);
+    let new = words(added);
+    w.set_color(&normal)?;
+    write!(w, "{}", if is_added { "+" } else { "-" })?;
+    let mut is_emphasized = false;
+    for word in diff::slice(&old, &new) {
+        let (word, emphasize) = match word {
+            diff::Result::Both(word, _) => (word, false),
+            diff::Result::Left(word) if !is_added => (word, true),
+            diff::Result::Right(word) if is_added => (word, true),
+            _ => continue,
+        };
+        if emphasize != is_emphasized {
+            w.set_color(if emphasize { &emphasized } else { &normal })?;
+            is_emphasized = emphasize;
+        }
+        write!(w, "{}", word)?;
+    }
+    w.reset()
+}
+
+/// Split the line into the words and the other characters.
+fn words(line: &str) -> Vec<&str> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut words = vec![];
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        let next = i + c.len_utf8();
+        if !is_word(c) {
+            if start < i {
+                words.push(&line[start..i]);
+            }
+            words.push(&line[i..next]);
+            start = next;
+        }
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+// ==== require_eq! ====
+
+/// The representations of compared value in the failure message of `require_eq!`.
+#[derive(Debug)]
+pub struct Repr {
+    /// The single-line representation.
+    compact: String,
+    /// The multi-line representation used to render the diff.
+    pretty: String,
+    /// Whether the value itself spans multiple lines.
+    multiline: bool,
+}
+
+impl Repr {
+    /// The maximum width of values printed without the diff.
+    const MAX_COMPACT_WIDTH: usize = 60;
+
+    fn is_compact(&self) -> bool {
+        !self.multiline && self.compact.chars().count() <= Self::MAX_COMPACT_WIDTH
+    }
+}
+
+/// The wrapper to select the representation of compared value.
+///
+/// The strings are compared as texts by `TextRepr`, and the other values
+/// are compared by their pretty-printed `Debug` representations.
+pub struct DiffValue<'a, T: ?Sized>(pub &'a T);
+
+pub trait TextRepr {
+    fn diff_repr(&self) -> Repr;
+}
+
+impl<T> TextRepr for DiffValue<'_, T>
+where
+    T: AsRef<str> + ?Sized,
+{
+    fn diff_repr(&self) -> Repr {
+        let text = self.0.as_ref();
+        Repr {
+            compact: format!("{:?}", text),
+            pretty: text.to_owned(),
+            multiline: text.contains('\n'),
+        }
+    }
+}
+
+pub trait DebugRepr {
+    fn diff_repr(&self) -> Repr;
+}
+
+impl<T> DebugRepr for &DiffValue<'_, T>
+where
+    T: fmt::Debug + ?Sized,
+{
+    fn diff_repr(&self) -> Repr {
+        Repr {
+            compact: format!("{:?}", self.0),
+            pretty: format!("{:#?}", self.0),
+            multiline: false,
+        }
+    }
+}
+
+/// Render the details of values compared by `require_eq!`.
+///
+/// The values are printed as is if both of them are short enough, and otherwise
+/// the diff of their multi-line representations is returned instead. The mismatch
+/// found by the approximate comparison is appended, if any.
+pub fn eq_failure(
+    left: Repr,
+    right: Repr,
+    mismatch: Option<ApproxError>,
+) -> (String, Option<Diff>) {
+    let (mut values, diff) = if left.is_compact() && right.is_compact() {
+        (
+            format!("\n   left: {}\n  right: {}", left.compact, right.compact),
+            None,
+        )
+    } else {
+        (
+            String::new(),
+            Some(Diff::new("left", "right", &left.pretty, &right.pretty)),
+        )
+    };
+    if let Some(mismatch) = mismatch {
+        values += &format!("\n   note: {}", mismatch);
+    }
+    (values, diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::{approx, ApproxMismatch as _, ApproxPair};
+    use termcolor::Buffer;
+
+    #[test]
+    fn diff_lines() {
+        assert_eq!(
+            Diff::new("expected", "actual", "a\nb\nc", "a\nB\nc").to_string(),
+            "--- expected\n+++ actual\n a\n-b\n+B\n c"
+        );
+    }
+
+    #[test]
+    fn split_words() {
+        assert_eq!(
+            words("  x: foo_bar(10),"),
+            vec![" ", " ", "x", ":", " ", "foo_bar", "(", "10", ")", ","]
+        );
+    }
+
+    #[test]
+    fn colored_diff() {
+        let diff = Diff::new("expected", "actual", "a\nfoo bar\nc", "a\nfoo baz\nc\nd");
+
+        let mut plain = Buffer::no_color();
+        diff.write_colored(&mut plain).unwrap();
+        assert_eq!(
+            String::from_utf8(plain.into_inner()).unwrap(),
+            diff.to_string()
+        );
+
+        let mut colored = Buffer::ansi();
+        diff.write_colored(&mut colored).unwrap();
+        let colored = String::from_utf8(colored.into_inner()).unwrap();
+        assert!(colored.starts_with("\x1b[0m\x1b[31m--- expected\x1b[0m\n"));
+        // the changed words are emphasized.
+        assert!(colored.contains("\x1b[31m-foo \x1b[0m\x1b[1m\x1b[4m\x1b[31mbar\x1b[0m"));
+        assert!(colored.contains("\x1b[32m+foo \x1b[0m\x1b[1m\x1b[4m\x1b[32mbaz\x1b[0m"));
+        assert!(colored.contains("\x1b[32m+d\x1b[0m"));
+    }
+
+    #[test]
+    fn eq_failures() {
+        assert_eq!(
+            eq_failure(
+                (&DiffValue(&vec![1, 2])).diff_repr(),
+                (&DiffValue(&vec![1, 3])).diff_repr(),
+                None,
+            ),
+            ("\n   left: [1, 2]\n  right: [1, 3]".into(), None)
+        );
+        assert_eq!(
+            eq_failure(
+                (&DiffValue("foo\nbar")).diff_repr(),
+                (&DiffValue(&String::from("foo\nbaz"))).diff_repr(),
+                None,
+            ),
+            (
+                String::new(),
+                Some(Diff::new("left", "right", "foo\nbar", "foo\nbaz"))
+            )
+        );
+        let long = "x".repeat(60);
+        let (values, diff) = eq_failure(
+            (&DiffValue(&Some((1, &long)))).diff_repr(),
+            (&DiffValue(&Some((2, &long)))).diff_repr(),
+            None,
+        );
+        assert_eq!(values, "");
+        assert_eq!(
+            diff.unwrap().to_string(),
+            format!(
+                "--- left\n+++ right\n Some(\n     (\n-        1,\n+        2,\n         {:?},\n     ),\n )",
+                long
+            )
+        );
+
+        // the approximate comparison reports the difference of values.
+        let (values, diff) = eq_failure(
+            (&DiffValue(&1.1)).diff_repr(),
+            (&DiffValue(&approx(1.0))).diff_repr(),
+            ApproxPair(&1.1, &approx(1.0)).approx_mismatch(),
+        );
+        assert_eq!(
+            values,
+            "\n   left: 1.1\n  right: approx(1.0)\n   note: 1.1 is not approximately equal to 1.0 (difference: 1.000e-1, tolerance: 1.192e-5)"
+        );
+        assert_eq!(diff, None);
+    }
+}
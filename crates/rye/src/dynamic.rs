@@ -0,0 +1,219 @@
+//! Discovery of the sections whose names are determined at runtime.
+//!
+//! The dynamic sections are not known before running the test case, so the
+//! test plan is run repeatedly until all of them are completed. In each run,
+//! only the first uncompleted section is entered at every level of nesting,
+//! and the others are left for the subsequent runs, like `DYNAMIC_SECTION`
+//! in Catch2.
+//!
+//! The names of dynamic sections must be the same in every run. Otherwise,
+//! the sections left in a run may never be found again, so the discovery fails
+//! when a run does not resume any of them or completes no sections.
+
+use std::{collections::HashSet, mem};
+
+/// The progress of dynamic sections in a test plan.
+#[derive(Debug, Default)]
+pub(crate) struct DynamicSections {
+    /// The paths of sections that have been completed in the previous runs.
+    completed: HashSet<Vec<String>>,
+    /// The paths of sections that have been left in the previous runs.
+    pending: HashSet<Vec<String>>,
+    /// The state of the current run.
+    run: Run,
+}
+
+#[derive(Debug, Default)]
+struct Run {
+    /// The path of dynamic sections currently entered.
+    path: Vec<String>,
+    /// The states of the current section and its ancestors, from the outermost
+    /// one.  The first element corresponds to the root of test plan.
+    frames: Vec<Frame>,
+    /// The paths of sections completed in this run.
+    completed: Vec<Vec<String>>,
+    /// The paths of sections left in this run.
+    pending: Vec<Vec<String>>,
+    /// Whether any of the sections left in the previous runs is entered.
+    resumed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Frame {
+    /// Whether a child section has been entered in this run.
+    entered: bool,
+    /// Whether any of child sections is left for the subsequent runs.
+    pending: bool,
+}
+
+impl DynamicSections {
+    /// Reset the state for starting a new run of the test plan.
+    pub(crate) fn start_run(&mut self) {
+        self.run = Run {
+            path: vec![],
+            frames: vec![Frame::default()],
+            ..Run::default()
+        };
+    }
+
+    /// Record the sections completed in the current run, and return whether
+    /// the test plan needs to be run again for the remaining sections.
+    ///
+    /// An error is returned if the run made no progress, in order not to run
+    /// the test plan forever.
+    pub(crate) fn finish_run(&mut self) -> anyhow::Result<bool> {
+        let run = mem::replace(&mut self.run, Run::default());
+        if !self.pending.is_empty() && !run.resumed {
+            anyhow::bail!(
+                "none of the dynamic sections left in the previous run is found again; \
+                 the names of dynamic sections must be the same in every run"
+            );
+        }
+        let pending = match run.frames.first() {
+            Some(root) => root.pending,
+            None => false,
+        };
+        if pending && run.completed.is_empty() {
+            anyhow::bail!("no dynamic sections are completed in the run of test plan");
+        }
+        self.completed.extend(run.completed);
+        self.pending.extend(run.pending);
+        Ok(pending)
+    }
+
+    /// Determine whether the section is entered in the current run.
+    pub(crate) fn enter(&mut self, name: &str) -> bool {
+        let run = &mut self.run;
+        let mut path = run.path.clone();
+        path.push(name.to_owned());
+        if self.completed.contains(&path) {
+            return false;
+        }
+
+        let parent = run
+            .frames
+            .last_mut()
+            .expect("the run of test plan is not started");
+        if parent.entered {
+            parent.pending = true;
+            run.pending.push(path);
+            return false;
+        }
+        parent.entered = true;
+        if self.pending.contains(&path) {
+            run.resumed = true;
+        }
+
+        run.path = path;
+        run.frames.push(Frame::default());
+        true
+    }
+
+    /// Leave the section entered by `enter`.
+    pub(crate) fn leave(&mut self) {
+        let run = &mut self.run;
+        let frame = run.frames.pop().expect("unbalanced dynamic sections");
+        if frame.pending {
+            // The parent section should be entered again to run the remaining ones.
+            if let Some(parent) = run.frames.last_mut() {
+                parent.pending = true;
+            }
+        } else {
+            run.completed.push(run.path.clone());
+        }
+        run.path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run the simulated test function until all sections are completed, and
+    /// return the names of sections entered in each run.
+    fn discover<F>(mut f: F) -> anyhow::Result<Vec<Vec<String>>>
+    where
+        F: FnMut(&mut DynamicSections, &mut Vec<String>),
+    {
+        let mut sections = DynamicSections::default();
+        let mut runs = vec![];
+        loop {
+            let mut entered = vec![];
+            sections.start_run();
+            f(&mut sections, &mut entered);
+            runs.push(entered);
+            if !sections.finish_run()? {
+                break Ok(runs);
+            }
+        }
+    }
+
+    fn section<F>(sections: &mut DynamicSections, entered: &mut Vec<String>, name: String, f: F)
+    where
+        F: FnOnce(&mut DynamicSections, &mut Vec<String>),
+    {
+        if sections.enter(&name) {
+            entered.push(name);
+            f(sections, entered);
+            sections.leave();
+        }
+    }
+
+    #[test]
+    fn no_sections() {
+        assert_eq!(discover(|_, _| ()).unwrap(), vec![Vec::<String>::new()]);
+    }
+
+    #[test]
+    fn sections_in_loop() {
+        let runs = discover(|sections, entered| {
+            for i in 0..3 {
+                section(sections, entered, format!("i = {}", i), |_, _| ());
+            }
+        })
+        .unwrap();
+        assert_eq!(runs, vec![vec!["i = 0"], vec!["i = 1"], vec!["i = 2"]]);
+    }
+
+    #[test]
+    fn nested_sections() {
+        let runs = discover(|sections, entered| {
+            for i in 0..2 {
+                section(
+                    sections,
+                    entered,
+                    format!("i = {}", i),
+                    |sections, entered| {
+                        for j in 0..2 {
+                            section(sections, entered, format!("j = {}", j), |_, _| ());
+                        }
+                    },
+                );
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            runs,
+            vec![
+                vec!["i = 0", "j = 0"],
+                vec!["i = 0", "j = 1"],
+                vec!["i = 1", "j = 0"],
+                vec!["i = 1", "j = 1"],
+            ]
+        );
+    }
+
+    #[test]
+    fn nondeterministic_names() {
+        let mut count = 0;
+        let err = discover(|sections, entered| {
+            for _ in 0..2 {
+                count += 1;
+                section(sections, entered, format!("count = {}", count), |_, _| ());
+            }
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("must be the same in every run"));
+        assert_eq!(count, 4);
+    }
+}
@@ -0,0 +1,273 @@
+//! Selection of the test cases and sections to be run.
+//!
+//! The filters are tested against the name of test cases and the paths of
+//! their sections, written as `test_name/section/nested section`. A test plan
+//! is selected when any filter matches the test name or the path of one of the
+//! sections it enters, and no skip filter matches them.
+//!
+//! The test cases and sections failed in the last run, selected by `--failed`,
+//! are matched by their exact paths in addition to the filters.
+
+use crate::test::{TestCase, TestDesc, TestPlan};
+use regex::Regex;
+
+/// The separator between the names in the path of sections.
+pub(crate) const SECTION_SEPARATOR: &str = "/";
+
+/// How the filter strings are interpreted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MatchMode {
+    /// Match the paths containing the filter as a substring.
+    Substring,
+    /// Match the paths equal to the filter.
+    Exact,
+    /// Match the paths by the filter as a regular expression.
+    Regex,
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Substring(String),
+    Exact(String),
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str, mode: MatchMode) -> anyhow::Result<Self> {
+        Ok(match mode {
+            MatchMode::Regex => Pattern::Regex(Regex::new(pattern).map_err(|err| {
+                anyhow::anyhow!("invalid regular expression {:?}: {}", pattern, err)
+            })?),
+            MatchMode::Exact => Pattern::Exact(pattern.into()),
+            MatchMode::Substring if pattern.contains(|c| c == '*' || c == '?') => {
+                Pattern::Glob(pattern.into())
+            }
+            MatchMode::Substring => Pattern::Substring(pattern.into()),
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Pattern::Substring(pattern) => path.contains(&**pattern),
+            Pattern::Exact(pattern) => path == pattern,
+            Pattern::Glob(pattern) => glob_matches(pattern, path),
+            Pattern::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// The filters specified by the command line arguments.
+#[derive(Debug, Default)]
+pub(crate) struct Filter {
+    patterns: Vec<Pattern>,
+    skip_patterns: Vec<Pattern>,
+    selections: Option<Vec<String>>,
+}
+
+impl Filter {
+    pub(crate) fn new(
+        patterns: &[String],
+        skip_patterns: &[String],
+        mode: MatchMode,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pat| Pattern::new(pat, mode))
+                .collect::<anyhow::Result<_>>()?,
+            skip_patterns: skip_patterns
+                .iter()
+                .map(|pat| Pattern::new(pat, mode))
+                .collect::<anyhow::Result<_>>()?,
+            selections: None,
+        })
+    }
+
+    /// Restrict the test plans to be run to the ones entering any of the
+    /// specified paths of sections.
+    ///
+    /// The plans whose innermost section contains the path are also selected,
+    /// so that the sections not known statically can be run again.
+    pub(crate) fn select_paths(&mut self, paths: impl IntoIterator<Item = String>) {
+        self.selections.get_or_insert_with(Vec::new).extend(paths);
+    }
+
+    /// Return the test plans of the test case that are selected by the filters.
+    ///
+    /// The test case is filtered out if the returned list is empty.
+    pub(crate) fn select_plans(&self, test: &'static TestCase) -> Vec<&'static TestPlan> {
+        test.plans
+            .iter()
+            .filter(|plan| self.is_selected(&plan_paths(&test.desc, plan)))
+            .collect()
+    }
+
+    fn is_selected(&self, paths: &[String]) -> bool {
+        let matches_any = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pat| paths.iter().any(|path| pat.matches(path)))
+        };
+        let innermost = format!("{}{}", paths[paths.len() - 1], SECTION_SEPARATOR);
+        let is_selected = match self.selections {
+            Some(ref selections) => selections
+                .iter()
+                .any(|selection| paths.contains(selection) || selection.starts_with(&innermost)),
+            None => true,
+        };
+        (self.patterns.is_empty() || matches_any(&self.patterns))
+            && !matches_any(&self.skip_patterns)
+            && is_selected
+    }
+}
+
+/// Return the path of sections entered by the test plan.
+pub(crate) fn plan_path(desc: &TestDesc, plan: &TestPlan) -> String {
+    plan_paths(desc, plan).pop().unwrap_or_default()
+}
+
+/// Return the test name and the paths of sections entered by the test plan,
+/// from the outermost one.
+fn plan_paths(desc: &TestDesc, plan: &TestPlan) -> Vec<String> {
+    let mut paths = vec![desc.name().to_owned()];
+    for id in plan.ancestors.iter().chain(plan.target.as_ref()) {
+        if let Some(section) = desc.sections.iter().find(|section| section.id == *id) {
+            let path = format!(
+                "{}{}{}",
+                paths[paths.len() - 1],
+                SECTION_SEPARATOR,
+                section.name
+            );
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Match the whole string with the glob pattern, where `*` matches any sequence
+/// of characters and `?` matches any single character.
+fn glob_matches(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+
+    let (mut pi, mut si) = (0, 0);
+    let mut last_star = None;
+    while si < s.len() {
+        match pattern.get(pi) {
+            Some(&'*') => {
+                last_star = Some((pi, si));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == s[si] => {
+                pi += 1;
+                si += 1;
+            }
+            _ => match last_star {
+                // Backtrack and let the last `*` consume one more character.
+                Some((star_pi, star_si)) => {
+                    last_star = Some((star_pi, star_si + 1));
+                    pi = star_pi + 1;
+                    si = star_si + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        assert!(glob_matches("net::*::timeout", "net::tcp::timeout"));
+        assert!(glob_matches("net::*::timeout", "net::tcp::v4::timeout"));
+        assert!(!glob_matches("net::*::timeout", "net::tcp::timeout_long"));
+        assert!(glob_matches("*_?", "case_1"));
+        assert!(!glob_matches("*_?", "case_10"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[test]
+    fn patterns() {
+        let matches =
+            |pattern: &str, mode, path: &str| Pattern::new(pattern, mode).unwrap().matches(path);
+        assert!(matches("tcp", MatchMode::Substring, "net::tcp::timeout"));
+        assert!(!matches("tcp", MatchMode::Exact, "net::tcp::timeout"));
+        assert!(matches("net::*", MatchMode::Substring, "net::tcp::timeout"));
+        assert!(!matches("net::*", MatchMode::Exact, "net::tcp::timeout"));
+        assert!(matches("net::*", MatchMode::Exact, "net::*"));
+        assert!(matches(
+            "^net::.*out$",
+            MatchMode::Regex,
+            "net::tcp::timeout"
+        ));
+        assert!(Pattern::new("(", MatchMode::Regex).is_err());
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn selected_paths() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut crate::Context<'_>) {
+            section!(ctx, "outer", {
+                section!(ctx, "inner1", {});
+                section!(ctx, "inner2", {});
+            });
+            section!(ctx, "other", {});
+        }
+
+        let name = test_case.desc.name();
+        let selected = |paths: &[&str]| {
+            let mut filter = Filter::default();
+            filter.select_paths(paths.iter().map(|path| format!("{}/{}", name, path)));
+            filter
+                .select_plans(&test_case)
+                .into_iter()
+                .map(|plan| plan_path(&test_case.desc, plan))
+                .collect::<Vec<_>>()
+        };
+        let path = |path: &str| format!("{}/{}", name, path);
+
+        assert_eq!(selected(&["outer/inner2"]), vec![path("outer/inner2")]);
+        assert_eq!(
+            selected(&["outer"]),
+            vec![path("outer/inner1"), path("outer/inner2")]
+        );
+        // the sections not known statically are run through the enclosing section.
+        assert_eq!(selected(&["other/dynamic"]), vec![path("other")]);
+        assert!(selected(&["outer/inner"]).is_empty());
+
+        let mut filter = Filter::default();
+        filter.select_paths(vec![name.to_owned()]);
+        assert_eq!(filter.select_plans(&test_case).len(), 3);
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn exact_paths_are_not_globs() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut crate::Context<'_>) {
+            section!(ctx, "what?", {});
+            section!(ctx, "whatx", {});
+        }
+
+        let path = format!("{}/what?", test_case.desc.name());
+        let selected = |mode| {
+            Filter::new(&[path.clone()], &[], mode)
+                .unwrap()
+                .select_plans(&test_case)
+                .into_iter()
+                .map(|plan| plan_path(&test_case.desc, plan))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(selected(MatchMode::Exact), vec![path.clone()]);
+        assert_eq!(selected(MatchMode::Substring).len(), 2);
+    }
+}
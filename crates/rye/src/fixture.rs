@@ -0,0 +1,205 @@
+#![allow(missing_docs)]
+
+use futures_core::future::BoxFuture;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+/// The type that represents a fixture defined by `#[rye::fixture]`.
+pub trait Fixture: 'static {
+    /// The type of value provided by this fixture.
+    type Output: Send + 'static;
+
+    #[doc(hidden)]
+    const DESC: FixtureDesc;
+}
+
+/// Metadata about a fixture.
+#[derive(Debug)]
+pub struct FixtureDesc {
+    pub name: &'static str,
+    pub type_id: fn() -> TypeId,
+    pub scope: FixtureScope,
+    pub setup: FixtureSetup,
+    pub teardown: Option<fn(FixtureValue) -> BoxFuture<'static, ()>>,
+}
+
+impl FixtureDesc {
+    pub(crate) fn requires_async(&self) -> bool {
+        match self.setup {
+            FixtureSetup::Async(..) => true,
+            FixtureSetup::Blocking(..) => self.teardown.is_some(),
+        }
+    }
+}
+
+/// The lifetime of the values provided by a fixture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FixtureScope {
+    /// The value is created for each execution of the test body, i.e. for each section.
+    Section,
+    /// The value is created once per test case and shared among its sections.
+    Test,
+    /// The value is created once per test session and shared among the test cases.
+    Session,
+}
+
+#[derive(Debug)]
+pub enum FixtureSetup {
+    Blocking(fn() -> FixtureValue),
+    Async(fn() -> BoxFuture<'static, FixtureValue>),
+}
+
+/// The fixture requested by a test case as its parameter.
+#[derive(Debug)]
+pub struct FixtureRequest {
+    pub desc: &'static FixtureDesc,
+    pub mutable: bool,
+}
+
+/// The type-erased value created by a fixture.
+pub enum FixtureValue {
+    Owned(Box<dyn Any + Send>),
+    Shared(Arc<dyn Any + Send + Sync>),
+}
+
+impl fmt::Debug for FixtureValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureValue::Owned(..) => f.write_str("FixtureValue::Owned(..)"),
+            FixtureValue::Shared(..) => f.write_str("FixtureValue::Shared(..)"),
+        }
+    }
+}
+
+impl FixtureValue {
+    #[inline]
+    pub fn owned<T>(value: T) -> Self
+    where
+        T: Send + 'static,
+    {
+        FixtureValue::Owned(Box::new(value))
+    }
+
+    #[inline]
+    pub fn shared<T>(value: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        FixtureValue::Shared(Arc::new(value))
+    }
+
+    pub fn into_owned<T>(self) -> Option<T>
+    where
+        T: Send + 'static,
+    {
+        match self {
+            FixtureValue::Owned(value) => value.downcast::<T>().ok().map(|value| *value),
+            FixtureValue::Shared(..) => None,
+        }
+    }
+
+    pub fn into_shared<T>(self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        match self {
+            FixtureValue::Shared(value) => match value.downcast::<T>().map(Arc::try_unwrap) {
+                Ok(Ok(value)) => Some(value),
+                Ok(Err(..)) => {
+                    eprintln!(
+                        "warning: the fixture value of type `{}` is still referenced and cannot be torn down",
+                        std::any::type_name::<T>()
+                    );
+                    None
+                }
+                Err(..) => None,
+            },
+            FixtureValue::Owned(..) => None,
+        }
+    }
+}
+
+/// The storage of fixture values owned by a running test case.
+#[derive(Clone, Default)]
+pub(crate) struct FixtureStore {
+    values: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>>,
+}
+
+impl FixtureStore {
+    pub(crate) fn insert(&self, type_id: TypeId, value: Box<dyn Any + Send>) {
+        self.values.lock().unwrap().insert(type_id, value);
+    }
+
+    pub(crate) fn take(&self, type_id: TypeId) -> Option<Box<dyn Any + Send>> {
+        self.values.lock().unwrap().remove(&type_id)
+    }
+}
+
+enum GuardValue<T> {
+    Owned(Option<T>),
+    Shared(Arc<dyn Any + Send + Sync>),
+}
+
+/// The reference to a fixture value passed to the test function.
+///
+/// The value is returned to the running test case when the guard is dropped.
+pub struct FixtureGuard<F: Fixture> {
+    value: GuardValue<F::Output>,
+    store: FixtureStore,
+}
+
+impl<F: Fixture> FixtureGuard<F> {
+    pub(crate) fn owned(value: F::Output, store: FixtureStore) -> Self {
+        Self {
+            value: GuardValue::Owned(Some(value)),
+            store,
+        }
+    }
+
+    pub(crate) fn shared(value: Arc<dyn Any + Send + Sync>, store: FixtureStore) -> Self {
+        Self {
+            value: GuardValue::Shared(value),
+            store,
+        }
+    }
+}
+
+impl<F: Fixture> Deref for FixtureGuard<F> {
+    type Target = F::Output;
+
+    fn deref(&self) -> &Self::Target {
+        match self.value {
+            GuardValue::Owned(ref value) => value.as_ref().expect("the value has been taken"),
+            GuardValue::Shared(ref value) => value
+                .downcast_ref::<F::Output>()
+                .expect("mismatched fixture type"),
+        }
+    }
+}
+
+impl<F: Fixture> DerefMut for FixtureGuard<F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self.value {
+            GuardValue::Owned(ref mut value) => value.as_mut().expect("the value has been taken"),
+            GuardValue::Shared(..) => panic!(
+                "the session-scoped fixture `{}` cannot be borrowed mutably",
+                F::DESC.name
+            ),
+        }
+    }
+}
+
+impl<F: Fixture> Drop for FixtureGuard<F> {
+    fn drop(&mut self) {
+        if let GuardValue::Owned(ref mut value) = self.value {
+            if let Some(value) = value.take() {
+                self.store.insert((F::DESC.type_id)(), Box::new(value));
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    fixture::{FixtureDesc, FixtureScope, FixtureSetup, FixtureValue},
+    test::TestDesc,
+};
+use futures_core::future::{BoxFuture, Future};
+use futures_util::lock::Mutex;
+use maybe_unwind::{maybe_unwind, FutureMaybeUnwindExt as _};
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    mem,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+type AnyValue = Arc<dyn Any + Send + Sync>;
+type InitFn = Box<dyn FnOnce() -> BoxFuture<'static, anyhow::Result<AnyValue>> + Send>;
+type TeardownFn = Box<dyn FnOnce(AnyValue) -> BoxFuture<'static, ()> + Send>;
+
+/// A session-wide value shared among the test cases.
+///
+/// The value is registered to the session by `Session::register_global`
+/// and is initialized lazily just before the first test case that requests it
+/// with `#[rye(global = Type)]` is started.  The test cases can access the value
+/// via `Context::global`.  The initialized values are torn down after all test
+/// cases are completed, in the reverse order of their initialization.
+pub struct Global<T> {
+    init: InitFn,
+    teardown: Option<TeardownFn>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> fmt::Debug for Global<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Global")
+            .field("type", &type_name::<T>())
+            .finish()
+    }
+}
+
+impl<T> Global<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Create a global value initialized by the specified function.
+    pub fn new<F, Fut>(init: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<T>> + Send + 'static,
+    {
+        Self {
+            init: Box::new(move || {
+                Box::pin(async move {
+                    let value = init().await?;
+                    Ok(Arc::new(value) as AnyValue)
+                })
+            }),
+            teardown: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Specify the function called at the end of the test session.
+    ///
+    /// If this function is not specified, the value is simply dropped.
+    pub fn teardown<F, Fut>(mut self, teardown: F) -> Self
+    where
+        F: FnOnce(T) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.teardown.replace(Box::new(move |value: AnyValue| {
+            Box::pin(async move {
+                let value = value
+                    .downcast::<T>()
+                    .unwrap_or_else(|_| unreachable!("mismatched global type"));
+                match Arc::try_unwrap(value) {
+                    Ok(value) => teardown(value).await,
+                    Err(..) => eprintln!(
+                        "warning: the global value of type `{}` is still referenced and cannot be torn down",
+                        type_name::<T>()
+                    ),
+                }
+            })
+        }));
+        self
+    }
+}
+
+/// The key to identify the global value requested by a test case.
+#[allow(missing_docs)]
+#[derive(Debug, Copy, Clone)]
+pub struct GlobalKey {
+    pub type_id: fn() -> TypeId,
+    pub type_name: fn() -> &'static str,
+}
+
+enum SlotState {
+    Uninit(InitFn, Option<TeardownFn>),
+    Ready(AnyValue, Option<TeardownFn>),
+    Failed(String),
+}
+
+struct Slot {
+    type_name: &'static str,
+    state: Mutex<SlotState>,
+}
+
+impl Slot {
+    fn from_fixture(desc: &'static FixtureDesc) -> Self {
+        let init: InitFn = Box::new(move || {
+            Box::pin(async move {
+                let value = match desc.setup {
+                    FixtureSetup::Blocking(setup) => setup(),
+                    FixtureSetup::Async(setup) => setup().await,
+                };
+                match value {
+                    FixtureValue::Shared(value) => Ok(value),
+                    FixtureValue::Owned(..) => Err(anyhow::anyhow!(
+                        "the session-scoped fixture `{}` provided a non-shared value",
+                        desc.name
+                    )),
+                }
+            })
+        });
+        let teardown = desc.teardown.map(|teardown| -> TeardownFn {
+            Box::new(move |value| teardown(FixtureValue::Shared(value)))
+        });
+        Self {
+            type_name: desc.name,
+            state: Mutex::new(SlotState::Uninit(init, teardown)),
+        }
+    }
+}
+
+/// The registry of global values used in a test session.
+///
+/// The session-scoped fixtures are also stored in this registry.
+#[derive(Default)]
+pub(crate) struct Globals {
+    slots: StdMutex<HashMap<TypeId, Arc<Slot>>>,
+    initialized: StdMutex<Vec<TypeId>>,
+}
+
+impl Globals {
+    pub(crate) fn register<T>(&mut self, global: Global<T>)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.slots.get_mut().unwrap().insert(
+            TypeId::of::<T>(),
+            Arc::new(Slot {
+                type_name: type_name::<T>(),
+                state: Mutex::new(SlotState::Uninit(global.init, global.teardown)),
+            }),
+        );
+    }
+
+    /// Acquire the global values and the session-scoped fixtures requested by a test case,
+    /// initializing them if necessary.
+    pub(crate) async fn acquire(&self, desc: &TestDesc) -> anyhow::Result<Acquired> {
+        let mut acquired = Acquired::default();
+        for key in desc.globals {
+            let type_id = (key.type_id)();
+            let slot = self
+                .slots
+                .lock()
+                .unwrap()
+                .get(&type_id)
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "the global value of type `{}` is not registered",
+                        (key.type_name)()
+                    )
+                })?;
+            let value = self.get(type_id, &slot).await?;
+            acquired.values.insert(type_id, value);
+        }
+
+        for request in desc.fixtures {
+            if request.desc.scope != FixtureScope::Session {
+                continue;
+            }
+            anyhow::ensure!(
+                !request.mutable,
+                "the session-scoped fixture `{}` cannot be borrowed mutably",
+                request.desc.name
+            );
+            let type_id = (request.desc.type_id)();
+            let slot = self
+                .slots
+                .lock()
+                .unwrap()
+                .entry(type_id)
+                .or_insert_with(|| Arc::new(Slot::from_fixture(request.desc)))
+                .clone();
+            let value = self.get(type_id, &slot).await?;
+            acquired.values.insert(type_id, value);
+        }
+
+        Ok(acquired)
+    }
+
+    async fn get(&self, type_id: TypeId, slot: &Slot) -> anyhow::Result<AnyValue> {
+        let mut state = slot.state.lock().await;
+        if let SlotState::Uninit(..) = *state {
+            let (init, teardown) = match mem::replace(
+                &mut *state,
+                SlotState::Failed("panicked during initialization".into()),
+            ) {
+                SlotState::Uninit(init, teardown) => (init, teardown),
+                _ => unreachable!(),
+            };
+            *state = match AssertUnwindSafe(init()).maybe_unwind().await {
+                Ok(Ok(value)) => {
+                    self.initialized.lock().unwrap().push(type_id);
+                    SlotState::Ready(value, teardown)
+                }
+                Ok(Err(err)) => SlotState::Failed(format!("{:#}", err)),
+                Err(unwind) => SlotState::Failed(format!("panicked: {}", unwind)),
+            };
+        }
+
+        match *state {
+            SlotState::Ready(ref value, ..) => Ok(value.clone()),
+            SlotState::Failed(ref msg) => Err(anyhow::anyhow!(
+                "failed to initialize `{}`: {}",
+                slot.type_name,
+                msg
+            )),
+            SlotState::Uninit(..) => unreachable!(),
+        }
+    }
+
+    /// Tear down the initialized values in the reverse order of initialization.
+    ///
+    /// The panics during teardown are caught so that the remaining values are
+    /// still torn down, and returned as the error messages.
+    pub(crate) async fn teardown(&self) -> Vec<String> {
+        let mut errors = vec![];
+        let initialized = mem::take(&mut *self.initialized.lock().unwrap());
+        for type_id in initialized.into_iter().rev() {
+            let slot = self.slots.lock().unwrap()[&type_id].clone();
+            let state = mem::replace(
+                &mut *slot.state.lock().await,
+                SlotState::Failed("already torn down".into()),
+            );
+            if let SlotState::Ready(value, teardown) = state {
+                let result = match teardown {
+                    Some(teardown) => AssertUnwindSafe(teardown(value)).maybe_unwind().await,
+                    None => maybe_unwind(AssertUnwindSafe(|| drop(value))),
+                };
+                if let Err(unwind) = result {
+                    errors.push(format!(
+                        "failed to tear down `{}`: panicked: {}",
+                        slot.type_name, unwind
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// The global values acquired by a test case.
+#[derive(Default)]
+pub(crate) struct Acquired {
+    values: HashMap<TypeId, AnyValue>,
+}
+
+impl Acquired {
+    pub(crate) fn get_any(&self, type_id: TypeId) -> Option<&AnyValue> {
+        self.values.get(&type_id)
+    }
+
+    pub(crate) fn get<T>(&self) -> anyhow::Result<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let value = self.values.get(&TypeId::of::<T>()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "the global value of type `{}` is not requested by this test case",
+                type_name::<T>()
+            )
+        })?;
+        Ok(value
+            .clone()
+            .downcast::<T>()
+            .unwrap_or_else(|_| unreachable!("mismatched global type")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn panicking_teardown() {
+        static TORN_DOWN: AtomicBool = AtomicBool::new(false);
+
+        struct First;
+        struct Second;
+
+        let mut globals = Globals::default();
+        globals.register(Global::new(|| async { Ok(First) }).teardown(|_| async {
+            TORN_DOWN.store(true, Ordering::SeqCst);
+        }));
+        globals.register(
+            Global::new(|| async { Ok(Second) }).teardown(|_| async { panic!("explicit panic") }),
+        );
+
+        let errors = block_on(async {
+            for &type_id in &[TypeId::of::<First>(), TypeId::of::<Second>()] {
+                let slot = globals.slots.lock().unwrap()[&type_id].clone();
+                drop(globals.get(type_id, &slot).await.unwrap());
+            }
+            globals.teardown().await
+        });
+
+        // `First` is torn down even though `Second`, initialized later, panicked.
+        assert!(TORN_DOWN.load(Ordering::SeqCst));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(&format!(
+            "failed to tear down `{}`: panicked: ",
+            type_name::<Second>()
+        )));
+        assert!(errors[0].contains("explicit panic"));
+    }
+}
@@ -0,0 +1,420 @@
+//! Execution of test cases in isolated subprocesses.
+//!
+//! In the isolation mode, the session re-executes the current test binary
+//! for each test case with the environment variable `RYE_ISOLATED_CHILD` set
+//! to the path of a result file. The child process runs only the specified
+//! test case and writes its outcome to the result file, which is read by the
+//! parent process after the child exits. The standard output and error of the
+//! child process are passed through as is.
+
+use crate::{
+    diff::Diff,
+    report::{Outcome, Reporter, Seeds, Summary, TestCaseSummary},
+    temp,
+    test::{Location, TestCase, TestDesc, TestOptions},
+};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+    sync::Mutex,
+};
+
+const CHILD_ENV: &str = "RYE_ISOLATED_CHILD";
+const RESULT_KEY: &str = "result";
+const FAILED_ATTEMPT_KEY: &str = "failed-attempt";
+const SECTION_KEY: &str = "section";
+
+/// Return whether the current process is a child process spawned for isolation.
+pub(crate) fn is_child() -> bool {
+    env::var_os(CHILD_ENV).is_some()
+}
+
+/// Run a test case in a subprocess and wait for its outcome.
+pub(crate) fn run_in_subprocess(
+    desc: &'static TestDesc,
+    filters: &[String],
+    options: TestOptions,
+) -> TestCaseSummary {
+    match spawn_child(desc, filters, options) {
+        Ok(summary) => summary,
+        Err(err) => TestCaseSummary {
+            desc,
+            outcome: Outcome::Errored(anyhow::anyhow!(
+                "failed to run the test case in a subprocess: {}",
+                err
+            )),
+            failed_attempts: vec![],
+            section_path: vec![],
+        },
+    }
+}
+
+fn spawn_child(
+    desc: &'static TestDesc,
+    filters: &[String],
+    options: TestOptions,
+) -> io::Result<TestCaseSummary> {
+    let result_dir = temp::create_temp_dir(desc.name())?;
+    let result_path = result_dir.join("result");
+
+    let mut cmd = Command::new(env::current_exe()?);
+    cmd.args(filters)
+        .arg("--exact")
+        .args(&["--color", "never"])
+        .arg("--seed")
+        .arg(options.seed.to_string())
+        .env(CHILD_ENV, &result_path)
+        .stdin(Stdio::null());
+    if options.keep_temp {
+        cmd.arg("--keep-temp");
+    }
+    if options.update_snapshots {
+        cmd.arg("--update-snapshots");
+    }
+    if options.retries > 0 {
+        cmd.arg("--retries").arg(options.retries.to_string());
+    }
+    if let Some(seed) = options.shuffle_seed {
+        cmd.arg("--shuffle-sections")
+            .arg("--shuffle-seed")
+            .arg(seed.to_string());
+    }
+
+    let status = cmd.status();
+    let records = match status {
+        Ok(..) => match fs::read_to_string(&result_path) {
+            Ok(records) => Ok(records),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(err),
+        },
+        Err(..) => Ok(String::new()),
+    };
+    let _ = fs::remove_dir_all(&result_dir);
+    let status = status?;
+    let records = records?;
+
+    let mut result = None;
+    let mut failed_attempts = vec![];
+    let mut section_path = vec![];
+    for record in records.lines() {
+        let mut parts = record.splitn(2, ':');
+        let key = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        match key {
+            FAILED_ATTEMPT_KEY => failed_attempts.push(decode_outcome(desc, value)),
+            SECTION_KEY => section_path.push(unescape(value)),
+            RESULT_KEY => result = Some(decode_outcome(desc, value)),
+            _ => (),
+        }
+    }
+
+    let outcome = match result {
+        Some(outcome) => outcome,
+        // The child process completed successfully, but the test case was not
+        // selected by the filters passed to it.
+        None if status.success() => Outcome::Errored(anyhow::anyhow!(
+            "the test case was not run in the subprocess (the filters matched no test cases: {})",
+            filters.join(", ")
+        )),
+        None => Outcome::Crashed(describe_status(status)).expect(desc),
+    };
+    Ok(TestCaseSummary {
+        desc,
+        outcome,
+        failed_attempts,
+        section_path,
+    })
+}
+
+#[cfg(unix)]
+fn describe_status(status: ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt as _;
+    match status.signal() {
+        Some(signal) => format!(
+            "the test process was terminated by signal {}{}",
+            signal,
+            signal_name(signal).map_or_else(String::new, |name| format!(" ({})", name))
+        ),
+        None => format!(
+            "the test process exited without reporting the result ({})",
+            status
+        ),
+    }
+}
+
+#[cfg(not(unix))]
+fn describe_status(status: ExitStatus) -> String {
+    format!(
+        "the test process exited without reporting the result ({})",
+        status
+    )
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    match signal {
+        4 => Some("SIGILL"),
+        6 => Some("SIGABRT"),
+        7 => Some("SIGBUS"),
+        8 => Some("SIGFPE"),
+        9 => Some("SIGKILL"),
+        11 => Some("SIGSEGV"),
+        15 => Some("SIGTERM"),
+        _ => None,
+    }
+}
+
+/// The reporter used in the child processes, which only writes the result
+/// to the file specified by the parent process.
+pub(crate) struct ChildReporter {
+    file: Mutex<File>,
+}
+
+impl ChildReporter {
+    pub(crate) fn new() -> io::Result<Self> {
+        let path = env::var_os(CHILD_ENV).map_or_else(PathBuf::new, PathBuf::from);
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Reporter for ChildReporter {
+    fn test_iteration_starting(&self, _: usize) {}
+
+    fn test_iterations_ended(&self, _: usize, _: &[(usize, Seeds)]) {}
+
+    fn test_run_starting(&self, _: &[&TestCase], _: Seeds) {}
+
+    fn test_run_ended(&self, _: &Summary, _: Seeds) {}
+
+    fn test_case_starting(&self, _: &TestDesc) {}
+
+    fn test_case_ended(&self, summary: &TestCaseSummary) {
+        let mut records = String::new();
+        for outcome in &summary.failed_attempts {
+            records += &format!("{}:{}\n", FAILED_ATTEMPT_KEY, encode_outcome(outcome));
+        }
+        for section in &summary.section_path {
+            records += &format!("{}:{}\n", SECTION_KEY, escape(section));
+        }
+        records += &format!("{}:{}\n", RESULT_KEY, encode_outcome(&summary.outcome));
+
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(records.as_bytes());
+        let _ = file.flush();
+    }
+}
+
+fn encode_outcome(outcome: &Outcome) -> String {
+    let (kind, message) = match outcome {
+        Outcome::Passed => ("passed", String::new()),
+        Outcome::Skipped { location, reason } => (
+            "skipped",
+            format!("{}\t{}", encode_location(location), escape(reason)),
+        ),
+        Outcome::Failed {
+            location,
+            reason,
+            diff,
+        } => {
+            let mut fields = format!("{}\t{}", encode_location(location), escape(reason));
+            if let Some(diff) = diff {
+                for field in &[&diff.old_label, &diff.new_label, &diff.old, &diff.new] {
+                    fields += "\t";
+                    fields += &escape(field);
+                }
+            }
+            ("failed", fields)
+        }
+        Outcome::Errored(err) => ("errored", escape(&format!("{:?}", err))),
+        Outcome::Panicked(message) => ("panicked", escape(message)),
+        Outcome::Crashed(message) => ("crashed", escape(message)),
+        // The original outcome follows the kind without escaping it twice.
+        Outcome::ExpectedFailure(outcome) => return format!("xfailed:{}", encode_outcome(outcome)),
+        Outcome::UnexpectedPass { .. } => ("xpassed", String::new()),
+    };
+    format!("{}:{}", kind, message)
+}
+
+fn decode_outcome(desc: &'static TestDesc, record: &str) -> Outcome {
+    let mut parts = record.splitn(2, ':');
+    let kind = parts.next().unwrap_or("");
+    let rest = parts.next().unwrap_or("");
+    match kind {
+        "passed" => return Outcome::Passed,
+        "errored" => return Outcome::Errored(anyhow::anyhow!("{}", unescape(rest))),
+        "panicked" => return Outcome::Panicked(unescape(rest)),
+        "crashed" => return Outcome::Crashed(unescape(rest)),
+        "xfailed" => return Outcome::ExpectedFailure(Box::new(decode_outcome(desc, rest))),
+        "xpassed" => {
+            return Outcome::UnexpectedPass {
+                reason: desc.xfail.unwrap_or_default(),
+            }
+        }
+        _ => (),
+    }
+
+    // The location and reason, followed by the labels and texts of the diff if any.
+    let fields: Vec<&str> = rest.split('\t').collect();
+    let location = match fields.get(..3) {
+        Some(&[file, line, column]) => decode_location(file, line, column),
+        _ => None,
+    };
+    let reason = fields.get(3).map(|reason| unescape(reason));
+    let diff = match fields.get(4..) {
+        Some(&[old_label, new_label, old, new]) => Some(Diff::new(
+            &unescape(old_label),
+            &unescape(new_label),
+            &unescape(old),
+            &unescape(new),
+        )),
+        _ => None,
+    };
+    match (kind, location, reason) {
+        ("skipped", Some(location), Some(reason)) if fields.len() == 4 => {
+            Outcome::Skipped { location, reason }
+        }
+        ("failed", Some(location), Some(reason)) if fields.len() == 4 || diff.is_some() => {
+            Outcome::Failed {
+                location,
+                reason,
+                diff,
+            }
+        }
+        _ => Outcome::Errored(anyhow::anyhow!(
+            "malformed result from the test process: {}",
+            record
+        )),
+    }
+}
+
+fn encode_location(location: &Location) -> String {
+    format!(
+        "{}\t{}\t{}",
+        escape(&location.file),
+        location.line,
+        location.column
+    )
+}
+
+fn decode_location(file: &str, line: &str, column: &str) -> Option<Location> {
+    Some(Location {
+        file: unescape(file).into(),
+        line: line.parse().ok()?,
+        column: column.parse().ok()?,
+    })
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some('t') => unescaped.push('\t'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn escape_roundtrip() {
+        let message = "line 1\nline 2\\n\r\n\tindented";
+        assert_eq!(unescape(&escape(message)), message);
+        assert!(!escape(message).contains('\n'));
+        assert!(!escape(message).contains('\t'));
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn outcome_roundtrip() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(_: &mut crate::Context<'_>) {}
+
+        static LOCATION: Location = Location {
+            file: Cow::Borrowed("src/tests\tfoo.rs"),
+            line: 10,
+            column: 5,
+        };
+        let desc = &test_case.desc;
+
+        let failed = Outcome::Failed {
+            location: LOCATION.clone(),
+            reason: "require_eq!(a, b) failed".into(),
+            diff: Some(Diff::new("left", "right", "a\n\tb", "a\nc")),
+        };
+        let encoded = encode_outcome(&Outcome::ExpectedFailure(Box::new(failed)));
+        assert!(!encoded.contains('\n'));
+        match decode_outcome(desc, &encoded) {
+            Outcome::ExpectedFailure(outcome) => match *outcome {
+                Outcome::Failed {
+                    location,
+                    reason,
+                    diff,
+                } => {
+                    assert_eq!(location.to_string(), "src/tests\tfoo.rs:10:5");
+                    assert_eq!(reason, "require_eq!(a, b) failed");
+                    assert_eq!(diff, Some(Diff::new("left", "right", "a\n\tb", "a\nc")));
+                }
+                outcome => panic!("unexpected outcome: {:?}", outcome),
+            },
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        let skipped = Outcome::Skipped {
+            location: &desc.location,
+            reason: "skipped".into(),
+        };
+        match decode_outcome(desc, &encode_outcome(&skipped)) {
+            Outcome::Skipped { location, reason } => {
+                assert!(std::ptr::eq(location, &desc.location));
+                assert_eq!(reason, "skipped");
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        let panicked = Outcome::Panicked("panicked at 'boom', src/lib.rs:1:1".into());
+        match decode_outcome(desc, &encode_outcome(&panicked)) {
+            Outcome::Panicked(message) => {
+                assert_eq!(message, "panicked at 'boom', src/lib.rs:1:1");
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        match decode_outcome(desc, "failed:broken") {
+            Outcome::Errored(..) => (),
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+}
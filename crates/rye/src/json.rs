@@ -0,0 +1,285 @@
+//! The machine-readable output in JSON.
+//!
+//! `JsonReporter` writes the events of a test session as a JSON object per
+//! line, similar to the unstable `--format json` of the built-in test harness.
+//! All events have the `type` and `event` fields, and the others depend on
+//! them:
+//!
+//! * `{"type":"suite","event":"started","test_count":..,"seed":..,"shuffle_seed":..}`
+//! * `{"type":"test","event":"started","name":..}`
+//! * `{"type":"test","event":"ok"|"failed"|"skipped"|"xfailed"|"xpassed","name":..,
+//!   "message":..,"location":..,"attempts":..,"flaky":..,"failed_attempts":[..],
+//!   "section_path":[..]}`
+//! * `{"type":"suite","event":"ok"|"failed","passed":..,"flaky":..,"failed":..,
+//!   "skipped":..,"xfailed":..,"xpassed":..,"filtered_out":..}`
+//! * `{"type":"iteration","event":"started","iteration":..}` and
+//!   `{"type":"iterations","event":"ok"|"failed","iterations":..,"failed_iterations":[..]}`
+//!   when the test cases are run repeatedly, where each failed iteration is
+//!   `{"iteration":..,"seed":..,"shuffle_seed":..}`.
+
+use crate::{
+    report::{Outcome, Reporter, Seeds, Summary, TestCaseSummary},
+    test::{Location, TestCase, TestDesc},
+};
+use std::{fmt::Write as _, io::Write, sync::Mutex};
+
+/// The reporter writing the events in JSON.
+pub(crate) struct JsonReporter<W> {
+    writer: Mutex<W>,
+}
+
+impl<W> JsonReporter<W>
+where
+    W: Write,
+{
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    fn write_event(&self, event: &str) {
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(writer, "{}", event);
+        let _ = writer.flush();
+    }
+}
+
+impl<W> Reporter for JsonReporter<W>
+where
+    W: Write,
+{
+    fn test_iteration_starting(&self, iteration: usize) {
+        self.write_event(&format!(
+            r#"{{"type":"iteration","event":"started","iteration":{}}}"#,
+            iteration
+        ));
+    }
+
+    fn test_iterations_ended(&self, num_iterations: usize, failed_iterations: &[(usize, Seeds)]) {
+        let failed: Vec<String> = failed_iterations
+            .iter()
+            .map(|&(iteration, seeds)| {
+                format!(r#"{{"iteration":{},{}}}"#, iteration, seeds_fields(seeds))
+            })
+            .collect();
+        self.write_event(&format!(
+            r#"{{"type":"iterations","event":"{}","iterations":{},"failed_iterations":[{}]}}"#,
+            if failed_iterations.is_empty() {
+                "ok"
+            } else {
+                "failed"
+            },
+            num_iterations,
+            failed.join(","),
+        ));
+    }
+
+    fn test_run_starting(&self, tests: &[&TestCase], seeds: Seeds) {
+        self.write_event(&format!(
+            r#"{{"type":"suite","event":"started","test_count":{},{}}}"#,
+            tests.len(),
+            seeds_fields(seeds),
+        ));
+    }
+
+    fn test_run_ended(&self, summary: &Summary, _: Seeds) {
+        self.write_event(&format!(
+            r#"{{"type":"suite","event":"{}","passed":{},"flaky":{},"failed":{},"skipped":{},"xfailed":{},"xpassed":{},"filtered_out":{}}}"#,
+            if summary.is_passed() { "ok" } else { "failed" },
+            summary.passed.len(),
+            summary.flaky.len(),
+            summary.failed.len(),
+            summary.skipped.len(),
+            summary.xfailed.len(),
+            summary.xpassed.len(),
+            summary.filtered_out.len(),
+        ));
+    }
+
+    fn test_case_starting(&self, desc: &TestDesc) {
+        self.write_event(&format!(
+            r#"{{"type":"test","event":"started","name":{}}}"#,
+            string(desc.name())
+        ));
+    }
+
+    fn test_case_ended(&self, summary: &TestCaseSummary) {
+        self.write_event(&test_case_summary(summary));
+    }
+}
+
+fn seeds_fields(seeds: Seeds) -> String {
+    format!(
+        r#""seed":{},"shuffle_seed":{}"#,
+        seeds.seed,
+        seeds
+            .shuffle_seed
+            .map_or_else(|| "null".into(), |seed| seed.to_string()),
+    )
+}
+
+fn test_case_summary(summary: &TestCaseSummary) -> String {
+    let event = match summary.outcome {
+        Outcome::Passed => "ok",
+        Outcome::Errored(..)
+        | Outcome::Failed { .. }
+        | Outcome::Panicked(..)
+        | Outcome::Crashed(..) => "failed",
+        Outcome::Skipped { .. } => "skipped",
+        Outcome::ExpectedFailure(..) => "xfailed",
+        Outcome::UnexpectedPass { .. } => "xpassed",
+    };
+    let failed_attempts: Vec<String> = summary
+        .failed_attempts
+        .iter()
+        .map(|outcome| format!("{{{}}}", outcome_fields(outcome)))
+        .collect();
+    let section_path: Vec<String> = summary.section_path.iter().map(|s| string(s)).collect();
+
+    let mut json = String::new();
+    let _ = write!(
+        json,
+        r#"{{"type":"test","event":"{}","name":{},{},"attempts":{},"flaky":{},"failed_attempts":[{}],"section_path":[{}]}}"#,
+        event,
+        string(summary.desc.name()),
+        outcome_fields(&summary.outcome),
+        summary.failed_attempts.len() + 1,
+        summary.is_flaky(),
+        failed_attempts.join(","),
+        section_path.join(","),
+    );
+    json
+}
+
+/// Render the `message` and `location` fields describing the outcome.
+fn outcome_fields(outcome: &Outcome) -> String {
+    format!(
+        r#""message":{},"location":{}"#,
+        outcome
+            .message()
+            .map_or_else(|| "null".into(), |message| string(&message)),
+        outcome.location().map_or_else(|| "null".into(), location),
+    )
+}
+
+pub(crate) fn location(location: &Location) -> String {
+    format!(
+        r#"{{"file":{},"line":{},"column":{}}}"#,
+        string(&location.file),
+        location.line,
+        location.column
+    )
+}
+
+pub(crate) fn string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_json_string() {
+        assert_eq!(string("foo"), r#""foo""#);
+        assert_eq!(string("\"quoted\"\\\n\u{1}"), r#""\"quoted\"\\\n\u0001""#);
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn test_case_events() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn flaky(_: &mut crate::Context<'_>) {}
+
+        let reporter = JsonReporter::new(vec![]);
+        reporter.test_case_starting(&flaky.desc);
+        reporter.test_case_ended(&TestCaseSummary {
+            desc: &flaky.desc,
+            outcome: Outcome::Passed,
+            failed_attempts: vec![Outcome::Failed {
+                location: flaky.desc.location.clone(),
+                reason: "failed at the first attempt".into(),
+                diff: None,
+            }],
+            section_path: vec!["section".into()],
+        });
+
+        let output = String::from_utf8(reporter.writer.into_inner().unwrap()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["type"], "test");
+        assert_eq!(events[0]["event"], "started");
+        assert_eq!(events[0]["name"], flaky.desc.name());
+
+        let ended = &events[1];
+        assert_eq!(ended["event"], "ok");
+        assert_eq!(ended["message"], serde_json::Value::Null);
+        assert_eq!(ended["attempts"], 2);
+        assert_eq!(ended["flaky"], true);
+        assert_eq!(
+            ended["failed_attempts"][0]["message"],
+            "failed at the first attempt"
+        );
+        assert_eq!(
+            ended["failed_attempts"][0]["location"]["line"],
+            flaky.desc.location.line
+        );
+        assert_eq!(ended["section_path"], serde_json::json!(["section"]));
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn skipped_after_failure_is_not_flaky() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn skipped(_: &mut crate::Context<'_>) {}
+
+        let summary = TestCaseSummary {
+            desc: &skipped.desc,
+            outcome: Outcome::Skipped {
+                location: skipped.desc.location.clone(),
+                reason: "skipped at the second attempt".into(),
+            },
+            failed_attempts: vec![Outcome::Failed {
+                location: skipped.desc.location.clone(),
+                reason: "failed at the first attempt".into(),
+                diff: None,
+            }],
+            section_path: vec![],
+        };
+        assert!(!summary.is_flaky());
+
+        let reporter = JsonReporter::new(vec![]);
+        reporter.test_case_ended(&summary);
+        let output = String::from_utf8(reporter.writer.into_inner().unwrap()).unwrap();
+        let ended: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(ended["event"], "skipped");
+        assert_eq!(ended["flaky"], false);
+
+        let mut results = Summary::empty();
+        results.append(summary);
+        assert!(results.flaky.is_empty());
+        assert_eq!(results.skipped.len(), 1);
+    }
+}
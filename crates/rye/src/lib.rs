@@ -11,14 +11,43 @@ a C++ unit testing framework library.
 
 #[macro_use]
 mod macros;
+mod approx;
+mod assertions;
+mod diff;
+mod dynamic;
+mod filter;
+mod fixture;
+mod global;
 mod harness;
+mod isolate;
+mod json;
+mod list;
+pub mod matchers;
+mod random;
 mod report;
 mod runner;
 mod session;
+mod skip;
+mod snapshot;
+mod state;
+mod temp;
 mod termination;
 mod test;
 
-pub use crate::{session::Session, termination::Termination, test::Context};
+pub use crate::{
+    approx::{approx, Approx, ApproxError, ApproxValue, Float},
+    filter::MatchMode,
+    fixture::{Fixture, FixtureGuard, FixtureScope},
+    global::Global,
+    random::Rng,
+    report::{Report, TestCaseSummary, TestStatus},
+    session::{ColorConfig, OutputFormat, Partition, Session, SessionConfig},
+    termination::{Termination, TestResult},
+    test::Context,
+};
+
+/// Define a fixture that provides values to the test cases.
+pub use rye_macros::fixture;
 
 /// Generate a single test case.
 pub use rye_macros::test;
@@ -37,14 +66,28 @@ hidden_item! {
             __section as section,
             __test_fn as test_fn,
             __test_name as test_name,
-            termination::Termination,
+            approx::{ApproxMismatch, ApproxPair, NoApproxMismatch},
+            assertions::{catch_panic, catch_panic_async, DowncastError},
+            diff::{eq_failure, DebugRepr, DiffValue, TextRepr},
+            fixture::{
+                Fixture, FixtureDesc, FixtureRequest, FixtureScope, FixtureSetup, FixtureValue,
+            },
+            global::GlobalKey,
+            matchers::check as check_matcher,
+            skip::SkipCondition,
+            termination::{Termination, TestResult},
             test::{
-                Context, Location, Section, TestCase, TestDesc, TestFn, TestName, TestPlan,
+                Context, Location, Resource, Section, TestCase, TestDesc, TestFn, TestName,
+                TestPlan,
             },
         };
+        pub use futures_core::future::BoxFuture;
         pub use std::{
-            boxed::Box, column, concat, file, format_args, line, module_path, result::Result, stringify,
+            any::{type_name, TypeId}, borrow::Cow, boxed::Box, column, concat, env, file, format, format_args,
+            line, module_path, option::Option, result::Result, stringify,
         };
+        #[cfg(feature = "serde")]
+        pub use crate::snapshot::to_json;
     }
 
     /// Re-exported items for #[test_main]
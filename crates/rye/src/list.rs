@@ -0,0 +1,140 @@
+//! Listing of the test cases for `--list`.
+//!
+//! With `--format json`, each test case is written as a JSON object per line
+//! with its name, location, kind, skip conditions, `xfail` reason and section
+//! tree. The `ignored` field is always `false` since no test cases are ignored
+//! in rye, and no `tags` field is written since rye has no tags.
+
+use crate::{
+    filter::SECTION_SEPARATOR,
+    json,
+    session::OutputFormat,
+    test::{Section, SectionId, TestCase, TestDesc, TestFn},
+};
+use std::{
+    fmt::Write as _,
+    io::{self, Write as _},
+};
+
+pub(crate) fn print_list(tests: &[&'static TestCase], format: OutputFormat) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut w = stdout.lock();
+
+    match format {
+        OutputFormat::Pretty | OutputFormat::Terse => {
+            for test in tests {
+                write!(w, "{}: test", test.desc.name())?;
+                if format == OutputFormat::Pretty && !test.desc.skip_conditions.is_empty() {
+                    write!(w, " (conditionally skipped)")?;
+                }
+                if format == OutputFormat::Pretty && test.desc.xfail.is_some() {
+                    write!(w, " (expected to fail)")?;
+                }
+                writeln!(w)?;
+            }
+            if format == OutputFormat::Pretty {
+                fn plural_suffix(n: usize) -> &'static str {
+                    match n {
+                        1 => "",
+                        _ => "s",
+                    }
+                }
+
+                if !tests.is_empty() {
+                    writeln!(w)?;
+                }
+                writeln!(w, "{} test{}", tests.len(), plural_suffix(tests.len()))?;
+            }
+        }
+        OutputFormat::Json => {
+            for test in tests {
+                writeln!(w, "{}", test_to_json(test))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn test_to_json(test: &TestCase) -> String {
+    let desc = &test.desc;
+    let kind = match test.testfn {
+        TestFn::Async(..) => "async",
+        TestFn::AsyncLocal(..) => "async_local",
+        TestFn::Blocking(..) => "blocking",
+    };
+
+    let mut json = String::new();
+    let _ = write!(
+        json,
+        r#"{{"type":"test","name":{},"location":{},"kind":"{}","ignored":false,"skip_conditions":{},"xfail":{},"sections":{}}}"#,
+        json::string(desc.name()),
+        json::location(&desc.location),
+        kind,
+        skip_conditions_to_json(desc),
+        desc.xfail.map_or_else(|| "null".into(), json::string),
+        sections_to_json(desc, &section_tree(test), None, desc.name()),
+    );
+    json
+}
+
+/// The conditions under which the test case is skipped at runtime.
+fn skip_conditions_to_json(desc: &TestDesc) -> String {
+    let conditions: Vec<String> = desc
+        .skip_conditions
+        .iter()
+        .map(|cond| {
+            let (kind, value) = cond.describe();
+            format!(r#"{{"kind":"{}","value":{}}}"#, kind, json::string(value))
+        })
+        .collect();
+    format!("[{}]", conditions.join(","))
+}
+
+/// Return the pairs of each section and its parent, collected from the test plans.
+fn section_tree(test: &TestCase) -> Vec<(SectionId, Option<SectionId>)> {
+    let mut tree = vec![];
+    for plan in test.plans {
+        let mut parent = None;
+        for &id in plan.ancestors.iter().chain(plan.target.as_ref()) {
+            if tree.iter().all(|&(section, _)| section != id) {
+                tree.push((id, parent));
+            }
+            parent = Some(id);
+        }
+    }
+    tree
+}
+
+fn sections_to_json(
+    desc: &TestDesc,
+    tree: &[(SectionId, Option<SectionId>)],
+    parent: Option<SectionId>,
+    parent_path: &str,
+) -> String {
+    let mut sections: Vec<&Section> = desc
+        .sections
+        .iter()
+        .filter(|section| tree.contains(&(section.id, parent)))
+        .collect();
+    sections.sort_by_key(|section| section.id);
+
+    let mut json = String::from("[");
+    for (i, section) in sections.into_iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        // The path of section can be passed as a filter to run only that section.
+        let path = format!("{}{}{}", parent_path, SECTION_SEPARATOR, section.name);
+        let _ = write!(
+            json,
+            r#"{{"name":{},"path":{},"location":{},"sections":{}}}"#,
+            json::string(section.name),
+            json::string(&path),
+            json::location(&section.location),
+            sections_to_json(desc, tree, Some(section.id), &path),
+        );
+    }
+    json.push(']');
+    json
+}
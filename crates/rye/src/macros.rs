@@ -12,28 +12,344 @@ macro_rules! hidden_item {
 /// This macro can usually be used to disable some test cases that may not
 /// success depending on the runtime context, such as network access or a
 /// certain secret variables is not set.
+///
+/// The execution is terminated by unwinding, so that this macro can also be
+/// used in the helper functions that receive the test context.
 #[macro_export]
 macro_rules! skip {
     ( $ctx:ident ) => {
-        $crate::skip!($ctx, "explicitly skipped");
+        $crate::skip!($ctx, "explicitly skipped")
     };
     ( $ctx:ident, $($arg:tt)+ ) => {{
         use $crate::_test_reexports as __rye;
         const LOCATION: __rye::Location = __rye::location!();
-        return $ctx.skip(&LOCATION, __rye::format_args!($($arg)+));
+        $ctx.skip(&LOCATION, __rye::format_args!($($arg)+))
     }};
 }
 
 /// Mark the current test case as failed and then terminate its execution.
+///
+/// Like `skip!`, this macro can also be used in the helper functions.
 #[macro_export]
 macro_rules! fail {
     ($ctx:ident) => {
-        $crate::fail!($ctx, "explicitly failed");
+        $crate::fail!($ctx, "explicitly failed")
     };
     ($ctx:ident, $($arg:tt)+) => {{
         use $crate::_test_reexports as __rye;
         const LOCATION: __rye::Location = __rye::location!();
-        return $ctx.fail(&LOCATION, __rye::format_args!($($arg)+));
+        $ctx.fail(&LOCATION, __rye::format_args!($($arg)+))
+    }};
+}
+
+/// Check that two values are equal, and fail the current test case if not.
+///
+/// The values must implement `PartialEq` and `Debug`. If either of them does not
+/// fit in a line, the failure message shows the line-level diff of their
+/// pretty-printed `{:#?}` representations, or of the texts themselves for the
+/// strings. The diffs are colored in the console output.
+#[macro_export]
+macro_rules! require_eq {
+    ($ctx:ident, $left:expr, $right:expr $(,)?) => {{
+        use __rye::{ApproxMismatch as _, DebugRepr as _, NoApproxMismatch as _, TextRepr as _};
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    let (values, diff) = __rye::eq_failure(
+                        (&__rye::DiffValue(left)).diff_repr(),
+                        (&__rye::DiffValue(right)).diff_repr(),
+                        (&__rye::ApproxPair(left, right)).approx_mismatch(),
+                    );
+                    $ctx.fail_with_diff(
+                        &LOCATION,
+                        __rye::format_args!(
+                            "require_eq!({}, {}) failed{}",
+                            __rye::stringify!($left),
+                            __rye::stringify!($right),
+                            values,
+                        ),
+                        diff,
+                    );
+                }
+            }
+        }
+    }};
+}
+
+/// Check that a value satisfies the matcher, and fail the current test case
+/// with the description of matcher if not.
+///
+/// The value must implement `Debug`. See the [`matchers`](crate::matchers)
+/// module for the available matchers.
+///
+/// ```ignore
+/// use rye::matchers::{contains, starts_with};
+///
+/// require_that!(ctx, s, starts_with("foo") & !contains("bar"));
+/// ```
+#[macro_export]
+macro_rules! require_that {
+    ($ctx:ident, $value:expr, $matcher:expr $(,)?) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        let value = &$value;
+        if let __rye::Result::Err(description) = __rye::check_matcher(&$matcher, value) {
+            $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_that!({}, {}) failed\n  value: {:?}\n  expected: {}",
+                    __rye::stringify!($value),
+                    __rye::stringify!($matcher),
+                    value,
+                    description,
+                ),
+            );
+        }
+    }};
+}
+
+/// Check that an expression panics, and fail the current test case if not.
+///
+/// The expression may be an async block, in which case this macro must be used
+/// in an async context. If a matcher is specified, the panic message must
+/// satisfy the matcher as well.
+///
+/// ```ignore
+/// use rye::matchers::contains;
+///
+/// require_panics!(ctx, vec![0u8; 3][4]);
+/// require_panics!(ctx, "x".parse::<i32>().unwrap(), contains("InvalidDigit"));
+/// require_panics!(ctx, async { client.get().await.unwrap() });
+/// ```
+#[macro_export]
+macro_rules! require_panics {
+    ($ctx:ident, async move $body:block $(, $matcher:expr)? $(,)?) => {
+        $crate::__require_panics!(
+            $ctx,
+            $crate::_test_reexports::catch_panic_async(async move $body).await,
+            $crate::_test_reexports::stringify!(async move $body)
+            $(, $matcher)?
+        )
+    };
+    ($ctx:ident, async $body:block $(, $matcher:expr)? $(,)?) => {
+        $crate::__require_panics!(
+            $ctx,
+            $crate::_test_reexports::catch_panic_async(async $body).await,
+            $crate::_test_reexports::stringify!(async $body)
+            $(, $matcher)?
+        )
+    };
+    ($ctx:ident, $e:expr $(, $matcher:expr)? $(,)?) => {
+        $crate::__require_panics!(
+            $ctx,
+            $crate::_test_reexports::catch_panic(|| $e),
+            $crate::_test_reexports::stringify!($e)
+            $(, $matcher)?
+        )
+    };
+}
+
+#[doc(hidden)] // private API
+#[macro_export]
+macro_rules! __require_panics {
+    ($ctx:ident, $result:expr, $expr:expr) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        if let __rye::Result::Ok(..) = $result {
+            $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_panics!({}) failed: the expression did not panic",
+                    $expr
+                ),
+            );
+        }
+    }};
+    ($ctx:ident, $result:expr, $expr:expr, $matcher:expr) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        match $result {
+            __rye::Result::Ok(..) => $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_panics!({}, {}) failed: the expression did not panic",
+                    $expr,
+                    __rye::stringify!($matcher),
+                ),
+            ),
+            __rye::Result::Err(message) => {
+                if let __rye::Result::Err(description) = __rye::check_matcher(&$matcher, &message) {
+                    $ctx.fail(
+                        &LOCATION,
+                        __rye::format_args!(
+                            "require_panics!({}, {}) failed\n  message: {:?}\n  expected: {}",
+                            $expr,
+                            __rye::stringify!($matcher),
+                            message,
+                            description,
+                        ),
+                    );
+                }
+            }
+        }
+    }};
+}
+
+/// Check that an expression returns an `Err`, and fail the current test case if not.
+///
+/// The error can be additionally checked by a pattern, or by its type with
+/// `type = T` if the error is type-erased, such as `anyhow::Error` and
+/// `Box<dyn Error>`. This macro evaluates to the error value.
+///
+/// ```ignore
+/// let err = require_err!(ctx, "x".parse::<i32>());
+/// require_err!(ctx, File::open("missing"), e if e.kind() == io::ErrorKind::NotFound);
+/// require_err!(ctx, load_config(), type = io::Error);
+/// ```
+#[macro_export]
+macro_rules! require_err {
+    ($ctx:ident, $e:expr, type = $t:ty $(,)?) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        match $e {
+            __rye::Result::Ok(..) => $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_err!({}, type = {}) failed: the expression returned Ok",
+                    __rye::stringify!($e),
+                    __rye::stringify!($t),
+                ),
+            ),
+            __rye::Result::Err(err) => {
+                if !__rye::DowncastError::is_type::<$t>(&err) {
+                    $ctx.fail(
+                        &LOCATION,
+                        __rye::format_args!(
+                            "require_err!({}, type = {}) failed: the error has another type\n  error: {:?}",
+                            __rye::stringify!($e),
+                            __rye::stringify!($t),
+                            err,
+                        ),
+                    );
+                }
+                err
+            }
+        }
+    }};
+    ($ctx:ident, $e:expr, $p:pat $(if $guard:expr)? $(,)?) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        match $e {
+            __rye::Result::Ok(..) => $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_err!({}, {}) failed: the expression returned Ok",
+                    __rye::stringify!($e),
+                    __rye::stringify!($p $(if $guard)?),
+                ),
+            ),
+            __rye::Result::Err(err) => {
+                #[allow(unused_variables, unreachable_patterns)]
+                match &err {
+                    $p $(if $guard)? => (),
+                    _ => $ctx.fail(
+                        &LOCATION,
+                        __rye::format_args!(
+                            "require_err!({}, {}) failed: the error does not match\n  error: {:?}",
+                            __rye::stringify!($e),
+                            __rye::stringify!($p $(if $guard)?),
+                            err,
+                        ),
+                    ),
+                }
+                err
+            }
+        }
+    }};
+    ($ctx:ident, $e:expr $(,)?) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        match $e {
+            __rye::Result::Ok(..) => $ctx.fail(
+                &LOCATION,
+                __rye::format_args!(
+                    "require_err!({}) failed: the expression returned Ok",
+                    __rye::stringify!($e),
+                ),
+            ),
+            __rye::Result::Err(err) => err,
+        }
+    }};
+}
+
+/// Describe a section whose name is determined at runtime.
+///
+/// Unlike `section!`, this macro can be used in loops and closures, and the
+/// name can be any value implementing `Display`. The dynamic sections are
+/// discovered while running the test case, and it is run repeatedly until all
+/// of them are executed, with only one of the sibling sections entered in
+/// each run. The dynamic sections can be nested, but `section!` cannot be
+/// described inside of them.
+///
+/// The block must not exit by `break`, `continue` or `return`, and the sections
+/// must be described in the same order on every run of the test case.
+///
+/// ```ignore
+/// for i in 0..3 {
+///     dynamic_section!(ctx, format_args!("i = {}", i), {
+///         // ...
+///     });
+/// }
+/// ```
+#[macro_export]
+macro_rules! dynamic_section {
+    ($ctx:ident, $name:expr, $block:block $(,)?) => {{
+        use $crate::_test_reexports as __rye;
+        let section = $ctx.enter_dynamic_section(__rye::format!("{}", $name));
+        if section.enabled() {
+            $block
+        }
+        $ctx.leave_dynamic_section(section);
+    }};
+}
+
+/// Compare a value with the snapshot stored next to the test source.
+///
+/// The value is serialized using its `Debug` implementation (pretty-printed)
+/// by default, or using `Display` with the `display =` prefix.  With the `serde`
+/// feature enabled, the `json =` prefix serializes the value into pretty JSON.
+///
+/// The snapshot files are stored in the `snapshots` directory next to the source
+/// file and are keyed by the test name and the path of the current section.
+/// On mismatch, the current test case fails with the diff of both snapshots
+/// and the new snapshot is written to the `.snap.new` file for review.
+/// Passing `--update-snapshots` or setting `RYE_UPDATE_SNAPSHOTS=1` accepts
+/// the new snapshots instead.
+#[macro_export]
+macro_rules! snapshot {
+    ($ctx:ident, debug = $value:expr) => {
+        $crate::__snapshot!($ctx, $crate::_test_reexports::format!("{:#?}", $value));
+    };
+    ($ctx:ident, display = $value:expr) => {
+        $crate::__snapshot!($ctx, $crate::_test_reexports::format!("{}", $value));
+    };
+    ($ctx:ident, json = $value:expr) => {
+        $crate::__snapshot!($ctx, $crate::_test_reexports::to_json(&$value));
+    };
+    ($ctx:ident, $value:expr) => {
+        $crate::snapshot!($ctx, debug = $value);
+    };
+}
+
+#[doc(hidden)] // private API
+#[macro_export]
+macro_rules! __snapshot {
+    ($ctx:ident, $content:expr) => {{
+        use $crate::_test_reexports as __rye;
+        const LOCATION: __rye::Location = __rye::location!();
+        $ctx.check_snapshot(__rye::env!("CARGO_MANIFEST_DIR"), &LOCATION, $content);
     }};
 }
 
@@ -83,7 +399,7 @@ macro_rules! __location {
     () => {{
         use $crate::_test_reexports as __rye;
         __rye::Location {
-            file: __rye::file!(),
+            file: __rye::Cow::Borrowed(__rye::file!()),
             line: __rye::line!(),
             column: __rye::column!(),
         }
@@ -0,0 +1,676 @@
+//! Composable matchers used with `require_that!`, like the matchers in Catch2.
+//!
+//! The built-in matchers can be combined with the operators `&` (both), `|`
+//! (either) and `!` (negation):
+//!
+//! ```
+//! use rye::matchers::{contains, starts_with, Matcher as _};
+//!
+//! let matcher = starts_with("foo") & !contains("bar");
+//! assert!(matcher.matches("foobaz"));
+//! assert!(!matcher.matches("foobar"));
+//! ```
+
+use regex::Regex;
+use std::{fmt, marker::PhantomData, ops};
+
+/// A condition on the values of type `T`, along with its description.
+///
+/// This trait can be implemented for user-defined matchers. The user-defined
+/// matchers can be combined with the others by [`all_of`], [`any_of`] and [`not`],
+/// whose results support the operators as well.
+///
+/// ```
+/// use rye::matchers::{not, Matcher};
+/// use std::fmt;
+///
+/// struct IsEven;
+///
+/// impl Matcher<i32> for IsEven {
+///     fn matches(&self, value: &i32) -> bool {
+///         value % 2 == 0
+///     }
+///
+///     fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str("is even")
+///     }
+/// }
+///
+/// assert!(IsEven.matches(&2));
+/// assert!(not(IsEven).matches(&3));
+/// ```
+pub trait Matcher<T: ?Sized> {
+    /// Return whether the value satisfies this matcher.
+    fn matches(&self, value: &T) -> bool;
+
+    /// Write the description of values satisfying this matcher, such as
+    /// `starts with "foo"`.
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+#[doc(hidden)] // private API
+pub fn check<M, T>(matcher: &M, value: &T) -> Result<(), String>
+where
+    M: Matcher<T>,
+    T: ?Sized,
+{
+    if matcher.matches(value) {
+        Ok(())
+    } else {
+        Err(Description::new(matcher).to_string())
+    }
+}
+
+struct Description<'a, M, T: ?Sized> {
+    matcher: &'a M,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<'a, M, T: ?Sized> Description<'a, M, T> {
+    fn new(matcher: &'a M) -> Self {
+        Self {
+            matcher,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M, T> fmt::Display for Description<'_, M, T>
+where
+    M: Matcher<T>,
+    T: ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.matcher.describe(f)
+    }
+}
+
+// ==== combinators ====
+
+/// Create a matcher that matches if both of the matchers match.
+///
+/// This is equivalent to `a & b`.
+pub fn all_of<A, B>(a: A, b: B) -> And<A, B> {
+    And(a, b)
+}
+
+/// Create a matcher that matches if either of the matchers matches.
+///
+/// This is equivalent to `a | b`.
+pub fn any_of<A, B>(a: A, b: B) -> Or<A, B> {
+    Or(a, b)
+}
+
+/// Create a matcher that matches if the matcher does not match.
+///
+/// This is equivalent to `!m`.
+pub fn not<M>(m: M) -> Not<M> {
+    Not(m)
+}
+
+/// The matcher created by [`all_of`] or `&`.
+#[derive(Debug, Clone)]
+pub struct And<A, B>(A, B);
+
+impl<T: ?Sized, A, B> Matcher<T> for And<A, B>
+where
+    A: Matcher<T>,
+    B: Matcher<T>,
+{
+    fn matches(&self, value: &T) -> bool {
+        self.0.matches(value) && self.1.matches(value)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(")?;
+        self.0.describe(f)?;
+        f.write_str(" and ")?;
+        self.1.describe(f)?;
+        f.write_str(")")
+    }
+}
+
+/// The matcher created by [`any_of`] or `|`.
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(A, B);
+
+impl<T: ?Sized, A, B> Matcher<T> for Or<A, B>
+where
+    A: Matcher<T>,
+    B: Matcher<T>,
+{
+    fn matches(&self, value: &T) -> bool {
+        self.0.matches(value) || self.1.matches(value)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(")?;
+        self.0.describe(f)?;
+        f.write_str(" or ")?;
+        self.1.describe(f)?;
+        f.write_str(")")
+    }
+}
+
+/// The matcher created by [`not`] or `!`.
+#[derive(Debug, Clone)]
+pub struct Not<M>(M);
+
+impl<T: ?Sized, M> Matcher<T> for Not<M>
+where
+    M: Matcher<T>,
+{
+    fn matches(&self, value: &T) -> bool {
+        !self.0.matches(value)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not ")?;
+        self.0.describe(f)
+    }
+}
+
+// ==== strings ====
+
+/// Create a matcher for the strings starting with the specified prefix.
+pub fn starts_with(prefix: impl Into<String>) -> StartsWith {
+    StartsWith(prefix.into())
+}
+
+/// The matcher created by [`starts_with`].
+#[derive(Debug, Clone)]
+pub struct StartsWith(String);
+
+impl<T> Matcher<T> for StartsWith
+where
+    T: AsRef<str> + ?Sized,
+{
+    fn matches(&self, value: &T) -> bool {
+        value.as_ref().starts_with(&*self.0)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "starts with {:?}", self.0)
+    }
+}
+
+/// Create a matcher for the strings ending with the specified suffix.
+pub fn ends_with(suffix: impl Into<String>) -> EndsWith {
+    EndsWith(suffix.into())
+}
+
+/// The matcher created by [`ends_with`].
+#[derive(Debug, Clone)]
+pub struct EndsWith(String);
+
+impl<T> Matcher<T> for EndsWith
+where
+    T: AsRef<str> + ?Sized,
+{
+    fn matches(&self, value: &T) -> bool {
+        value.as_ref().ends_with(&*self.0)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ends with {:?}", self.0)
+    }
+}
+
+/// Create a matcher for the strings containing the specified substring, or
+/// the collections containing the specified element.
+pub fn contains<T>(needle: T) -> Contains<T> {
+    Contains(needle)
+}
+
+/// The matcher created by [`contains`].
+#[derive(Debug, Clone)]
+pub struct Contains<T>(T);
+
+macro_rules! impl_contains_for_strings {
+    ($($t:ty),*) => {$(
+        impl<N> Matcher<$t> for Contains<N>
+        where
+            N: AsRef<str>,
+        {
+            fn matches(&self, value: &$t) -> bool {
+                AsRef::<str>::as_ref(value).contains(self.0.as_ref())
+            }
+
+            fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "contains {:?}", self.0.as_ref())
+            }
+        }
+    )*};
+}
+
+impl_contains_for_strings!(str, String, &str, &String);
+
+macro_rules! impl_contains_for_collections {
+    ($( [$($generics:tt)*] $t:ty, )*) => {$(
+        impl<$($generics)* T> Matcher<$t> for Contains<T>
+        where
+            T: PartialEq + fmt::Debug,
+        {
+            fn matches(&self, value: &$t) -> bool {
+                value.iter().any(|item| *item == self.0)
+            }
+
+            fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "contains {:?}", self.0)
+            }
+        }
+    )*};
+}
+
+impl_contains_for_collections! {
+    [] [T],
+    [] Vec<T>,
+    ['a,] &'a [T],
+    ['a,] &'a Vec<T>,
+}
+
+macro_rules! impl_contains_for_arrays {
+    ($($n:expr)*) => {
+        impl_contains_for_collections! { $( [] [T; $n], )* }
+    };
+}
+
+impl_contains_for_arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+/// Create a matcher for the strings matching the specified regular expression.
+///
+/// # Panics
+///
+/// This function panics if the pattern is not a valid regular expression.
+pub fn matches_regex(pattern: &str) -> MatchesRegex {
+    match Regex::new(pattern) {
+        Ok(regex) => MatchesRegex(regex),
+        Err(err) => panic!("invalid regular expression {:?}: {}", pattern, err),
+    }
+}
+
+/// The matcher created by [`matches_regex`].
+#[derive(Debug, Clone)]
+pub struct MatchesRegex(Regex);
+
+impl<T> Matcher<T> for MatchesRegex
+where
+    T: AsRef<str> + ?Sized,
+{
+    fn matches(&self, value: &T) -> bool {
+        self.0.is_match(value.as_ref())
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "matches /{}/", self.0)
+    }
+}
+
+// ==== collections ====
+
+/// Collections whose elements are matched by the collection matchers.
+pub trait Collection {
+    /// The type of elements.
+    type Item;
+
+    /// Return the elements as a slice.
+    fn as_slice(&self) -> &[Self::Item];
+}
+
+impl<T> Collection for [T] {
+    type Item = T;
+
+    fn as_slice(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> Collection for Vec<T> {
+    type Item = T;
+
+    fn as_slice(&self) -> &[T] {
+        self
+    }
+}
+
+impl<C> Collection for &C
+where
+    C: Collection + ?Sized,
+{
+    type Item = C::Item;
+
+    fn as_slice(&self) -> &[C::Item] {
+        (**self).as_slice()
+    }
+}
+
+macro_rules! impl_collection_for_arrays {
+    ($($n:expr)*) => {$(
+        impl<T> Collection for [T; $n] {
+            type Item = T;
+
+            fn as_slice(&self) -> &[T] {
+                self
+            }
+        }
+    )*};
+}
+
+impl_collection_for_arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+/// Create a matcher for the collections having the same elements as
+/// the specified ones, regardless of their order.
+pub fn unordered_equals<T>(expected: impl IntoIterator<Item = T>) -> UnorderedEquals<T> {
+    UnorderedEquals(expected.into_iter().collect())
+}
+
+/// The matcher created by [`unordered_equals`].
+#[derive(Debug, Clone)]
+pub struct UnorderedEquals<T>(Vec<T>);
+
+impl<C, T> Matcher<C> for UnorderedEquals<T>
+where
+    C: Collection + ?Sized,
+    C::Item: PartialEq<T>,
+    T: fmt::Debug,
+{
+    fn matches(&self, value: &C) -> bool {
+        let value = value.as_slice();
+        if value.len() != self.0.len() {
+            return false;
+        }
+        let mut used = vec![false; self.0.len()];
+        value.iter().all(|item| {
+            let found = self
+                .0
+                .iter()
+                .zip(&mut used)
+                .find(|(expected, used)| !**used && *item == **expected);
+            match found {
+                Some((_, used)) => {
+                    *used = true;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "equals {:?} in any order", self.0)
+    }
+}
+
+/// Create a matcher for the collections sorted in ascending order.
+pub fn is_sorted() -> IsSorted {
+    IsSorted(())
+}
+
+/// The matcher created by [`is_sorted`].
+#[derive(Debug, Clone)]
+pub struct IsSorted(());
+
+impl<C> Matcher<C> for IsSorted
+where
+    C: Collection + ?Sized,
+    C::Item: PartialOrd,
+{
+    fn matches(&self, value: &C) -> bool {
+        value.as_slice().windows(2).all(|w| w[0] <= w[1])
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("is sorted")
+    }
+}
+
+// ==== predicates ====
+
+/// Create a matcher from a predicate function and its description.
+pub fn predicate<F>(description: impl Into<String>, f: F) -> Predicate<F> {
+    Predicate {
+        description: description.into(),
+        f,
+    }
+}
+
+/// The matcher created by [`predicate`].
+#[derive(Clone)]
+pub struct Predicate<F> {
+    description: String,
+    f: F,
+}
+
+impl<F> fmt::Debug for Predicate<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Predicate")
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl<T, F> Matcher<T> for Predicate<F>
+where
+    T: ?Sized,
+    F: Fn(&T) -> bool,
+{
+    fn matches(&self, value: &T) -> bool {
+        (self.f)(value)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+/// Create a matcher for the values equal to the specified one.
+pub fn equals<T>(expected: T) -> Equals<T> {
+    Equals(expected)
+}
+
+/// The matcher created by [`equals`].
+#[derive(Debug, Clone)]
+pub struct Equals<T>(T);
+
+impl<T, U> Matcher<T> for Equals<U>
+where
+    T: PartialEq<U> + ?Sized,
+    U: fmt::Debug,
+{
+    fn matches(&self, value: &T) -> bool {
+        *value == self.0
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "equals {:?}", self.0)
+    }
+}
+
+// ==== errors ====
+
+/// Create a matcher for the `Ok` values matching the specified matcher.
+pub fn ok<M>(m: M) -> IsOk<M> {
+    IsOk(m)
+}
+
+/// The matcher created by [`ok`].
+#[derive(Debug, Clone)]
+pub struct IsOk<M>(M);
+
+impl<T, E, M> Matcher<Result<T, E>> for IsOk<M>
+where
+    M: Matcher<T>,
+{
+    fn matches(&self, value: &Result<T, E>) -> bool {
+        match value {
+            Ok(value) => self.0.matches(value),
+            Err(..) => false,
+        }
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("is Ok and ")?;
+        self.0.describe(f)
+    }
+}
+
+/// Create a matcher for the `Err` values matching the specified matcher.
+pub fn err<M>(m: M) -> IsErr<M> {
+    IsErr(m)
+}
+
+/// The matcher created by [`err`].
+#[derive(Debug, Clone)]
+pub struct IsErr<M>(M);
+
+impl<T, E, M> Matcher<Result<T, E>> for IsErr<M>
+where
+    M: Matcher<E>,
+{
+    fn matches(&self, value: &Result<T, E>) -> bool {
+        match value {
+            Ok(..) => false,
+            Err(err) => self.0.matches(err),
+        }
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("is Err and ")?;
+        self.0.describe(f)
+    }
+}
+
+/// Create a matcher for the values whose string representations, such as
+/// the messages of errors, match the specified matcher.
+pub fn message<M>(m: M) -> Message<M> {
+    Message(m)
+}
+
+/// The matcher created by [`message`].
+#[derive(Debug, Clone)]
+pub struct Message<M>(M);
+
+impl<T, M> Matcher<T> for Message<M>
+where
+    T: fmt::Display + ?Sized,
+    M: Matcher<String>,
+{
+    fn matches(&self, value: &T) -> bool {
+        self.0.matches(&value.to_string())
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("has message that ")?;
+        self.0.describe(f)
+    }
+}
+
+// ==== operators ====
+
+macro_rules! impl_ops {
+    ($( [$($generics:ident),*] $t:ty, )*) => {$(
+        impl<$($generics,)* Rhs> ops::BitAnd<Rhs> for $t {
+            type Output = And<Self, Rhs>;
+
+            fn bitand(self, rhs: Rhs) -> Self::Output {
+                And(self, rhs)
+            }
+        }
+
+        impl<$($generics,)* Rhs> ops::BitOr<Rhs> for $t {
+            type Output = Or<Self, Rhs>;
+
+            fn bitor(self, rhs: Rhs) -> Self::Output {
+                Or(self, rhs)
+            }
+        }
+
+        impl<$($generics),*> ops::Not for $t {
+            type Output = Not<Self>;
+
+            fn not(self) -> Self::Output {
+                Not(self)
+            }
+        }
+    )*};
+}
+
+impl_ops! {
+    [A, B] And<A, B>,
+    [A, B] Or<A, B>,
+    [M] Not<M>,
+    [] StartsWith,
+    [] EndsWith,
+    [T] Contains<T>,
+    [] MatchesRegex,
+    [T] UnorderedEquals<T>,
+    [] IsSorted,
+    [F] Predicate<F>,
+    [T] Equals<T>,
+    [M] IsOk<M>,
+    [M] IsErr<M>,
+    [M] Message<M>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe<M: Matcher<T>, T: ?Sized>(matcher: &M, _: &T) -> String {
+        Description::new(matcher).to_string()
+    }
+
+    #[test]
+    fn strings() {
+        let matcher = starts_with("foo") & !contains("bar");
+        assert!(matcher.matches("foobaz"));
+        assert!(!matcher.matches(&String::from("foobar")));
+        assert!(!matcher.matches(&"bazfoo"));
+        assert_eq!(
+            describe(&matcher, "foo"),
+            r#"(starts with "foo" and not contains "bar")"#
+        );
+
+        assert!((ends_with(".rs") | matches_regex(r"^\d+$")).matches("123"));
+        assert!(!matches_regex(r"^\d+$").matches("12a"));
+    }
+
+    #[test]
+    fn collections() {
+        assert!(contains(2).matches(&vec![1, 2, 3]));
+        assert!(!contains(4).matches(&[1, 2, 3]));
+        assert!(unordered_equals(vec![3, 1, 2]).matches(&[1, 2, 3]));
+        assert!(!unordered_equals(vec![1, 1, 2]).matches(&[1, 2, 2]));
+        assert!(is_sorted().matches(&[1, 2, 2, 3][..]));
+        assert!(!is_sorted().matches(&vec![2, 1]));
+        assert_eq!(
+            describe(&unordered_equals(vec![1, 2]), &[1, 2]),
+            "equals [1, 2] in any order"
+        );
+    }
+
+    #[test]
+    fn predicates_and_errors() {
+        let is_even = predicate("is even", |n: &i32| n % 2 == 0);
+        assert!((is_even.clone() | equals(3)).matches(&3));
+        assert!(!(is_even & equals(3)).matches(&3));
+
+        let res: Result<(), std::io::Error> = Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "file not found",
+        ));
+        let matcher = err(message(contains("not found")));
+        assert!(matcher.matches(&res));
+        assert!(!ok(equals(())).matches(&res));
+        assert_eq!(
+            describe(&matcher, &res),
+            r#"is Err and has message that contains "not found""#
+        );
+    }
+}
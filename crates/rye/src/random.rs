@@ -0,0 +1,91 @@
+use rand::SeedableRng as _;
+use rand_pcg::Pcg64Mcg;
+use std::{
+    env, process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of environment variable to specify the seed of the test run.
+const SEED_ENV: &str = "RYE_SEED";
+
+/// The pseudo random number generator returned by `Context::rng`.
+///
+/// It implements the traits in `rand` 0.7, such as `RngCore` and `Rng`.
+pub type Rng = Pcg64Mcg;
+
+/// Return the seed of the test run specified by the environment variable.
+pub(crate) fn seed_from_env() -> anyhow::Result<Option<u64>> {
+    match env::var(SEED_ENV) {
+        Ok(value) => value.parse().map(Some).map_err(|err| {
+            anyhow::anyhow!(
+                "{} must be an unsigned integer (was {}): {}",
+                SEED_ENV,
+                value,
+                err
+            )
+        }),
+        Err(..) => Ok(None),
+    }
+}
+
+/// Generate a new seed from the current time and process ID.
+pub(crate) fn generate_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    hash(nanos, &process::id().to_le_bytes())
+}
+
+/// Derive the seed used in the specified iteration of repeated test runs.
+///
+/// The first iteration uses the seed as is, so that the failure in an iteration
+/// can be reproduced by running the test cases once with the derived seed.
+pub(crate) fn derive_seed(seed: u64, iteration: usize) -> u64 {
+    match iteration {
+        0 | 1 => seed,
+        n => hash(seed, &(n as u64).to_le_bytes()),
+    }
+}
+
+/// Create a random number generator seeded with the session-level seed and the key.
+///
+/// The same pair of seed and key always results in the same sequence of random
+/// numbers, regardless of the order of the test cases.
+pub(crate) fn rng(seed: u64, key: &str) -> Rng {
+    Rng::seed_from_u64(hash(seed, key.as_bytes()))
+}
+
+/// Mix the seed and the bytes using FNV-1a.
+pub(crate) fn hash(seed: u64, bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    seed.to_le_bytes()
+        .iter()
+        .chain(bytes)
+        .fold(FNV_OFFSET_BASIS, |hash, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore as _;
+
+    #[test]
+    fn rng_is_reproducible() {
+        assert_eq!(rng(42, "foo").next_u64(), rng(42, "foo").next_u64());
+        assert_ne!(rng(42, "foo").next_u64(), rng(42, "bar").next_u64());
+        assert_ne!(rng(42, "foo").next_u64(), rng(43, "foo").next_u64());
+    }
+
+    #[test]
+    fn derived_seeds() {
+        assert_eq!(derive_seed(42, 1), 42);
+        assert_eq!(derive_seed(42, 2), derive_seed(42, 2));
+        assert_ne!(derive_seed(42, 2), 42);
+        assert_ne!(derive_seed(42, 2), derive_seed(42, 3));
+        assert_ne!(derive_seed(42, 2), derive_seed(43, 2));
+    }
+}
@@ -1,34 +1,174 @@
 #![allow(missing_docs)]
 
-use crate::test::{Location, TestCase, TestDesc};
-use maybe_unwind::Unwind;
+use crate::{
+    diff::Diff,
+    test::{Location, TestCase, TestDesc},
+};
 
 #[derive(Debug)]
 pub(crate) enum Outcome {
     Passed,
     Errored(anyhow::Error),
     Skipped {
-        location: &'static Location,
+        location: Location,
         reason: String,
     },
     Failed {
-        location: &'static Location,
+        location: Location,
         reason: String,
+        /// The diff of compared values, rendered after the reason.
+        diff: Option<Diff>,
     },
-    Panicked(Unwind),
+    /// The test case panicked, with the rendered panic information.
+    Panicked(String),
+    /// The test process terminated abnormally in the isolation mode.
+    Crashed(String),
+    /// The test case marked as `xfail` failed as expected.
+    ExpectedFailure(Box<Outcome>),
+    /// The test case marked as `xfail` passed unexpectedly.
+    UnexpectedPass {
+        reason: &'static str,
+    },
+}
+
+impl Outcome {
+    /// Return whether the outcome should be reported as a failure.
+    pub(crate) fn is_failed(&self) -> bool {
+        match self {
+            Outcome::Passed | Outcome::Skipped { .. } | Outcome::ExpectedFailure(..) => false,
+            Outcome::Errored(..)
+            | Outcome::Failed { .. }
+            | Outcome::Panicked(..)
+            | Outcome::Crashed(..)
+            | Outcome::UnexpectedPass { .. } => true,
+        }
+    }
+
+    /// Return the message describing the outcome, if any.
+    pub(crate) fn message(&self) -> Option<String> {
+        match self {
+            Outcome::Passed => None,
+            Outcome::Errored(err) => Some(format!("{:?}", err)),
+            Outcome::Skipped { reason, .. } => Some(reason.clone()),
+            Outcome::Failed { reason, diff, .. } => Some(match diff {
+                Some(diff) => format!("{}\n{}", reason, diff),
+                None => reason.clone(),
+            }),
+            Outcome::Panicked(message) | Outcome::Crashed(message) => Some(message.clone()),
+            Outcome::ExpectedFailure(outcome) => outcome.message(),
+            Outcome::UnexpectedPass { reason } => Some(format!(
+                "the test case is expected to fail ({}), but passed",
+                reason
+            )),
+        }
+    }
+
+    /// Return the location where the test case is failed or skipped, if known.
+    pub(crate) fn location(&self) -> Option<&Location> {
+        match self {
+            Outcome::Skipped { location, .. } | Outcome::Failed { location, .. } => Some(location),
+            Outcome::ExpectedFailure(outcome) => outcome.location(),
+            _ => None,
+        }
+    }
+
+    /// Interpret the final outcome of the test case with the expectation of `xfail`.
+    pub(crate) fn expect(self, desc: &TestDesc) -> Self {
+        let reason = match desc.xfail {
+            Some(reason) => reason,
+            None => return self,
+        };
+        match self {
+            Outcome::Passed => Outcome::UnexpectedPass { reason },
+            outcome @ Outcome::Errored(..)
+            | outcome @ Outcome::Failed { .. }
+            | outcome @ Outcome::Panicked(..)
+            | outcome @ Outcome::Crashed(..) => Outcome::ExpectedFailure(Box::new(outcome)),
+            outcome => outcome,
+        }
+    }
+}
+
+/// The final status of a test case.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TestStatus {
+    /// The test case passed.
+    Passed,
+    /// The test case failed by an assertion or `fail!`.
+    Failed,
+    /// The test case returned an error.
+    Errored,
+    /// The test case panicked.
+    Panicked,
+    /// The test process terminated abnormally in the isolation mode.
+    Crashed,
+    /// The test case was skipped.
+    Skipped,
+    /// The test case marked as `xfail` failed as expected.
+    ExpectedFailure,
+    /// The test case marked as `xfail` passed unexpectedly.
+    UnexpectedPass,
 }
 
 #[derive(Debug)]
 pub struct TestCaseSummary {
     pub(crate) desc: &'static TestDesc,
     pub(crate) outcome: Outcome,
+    /// The outcomes of the earlier attempts failed before retrying.
+    pub(crate) failed_attempts: Vec<Outcome>,
+    /// The path of sections where the last attempt ended.
+    pub(crate) section_path: Vec<String>,
+}
+
+impl TestCaseSummary {
+    /// Return the name of test case.
+    pub fn name(&self) -> &str {
+        self.desc.name()
+    }
+
+    /// Return the final status of the test case.
+    pub fn status(&self) -> TestStatus {
+        match self.outcome {
+            Outcome::Passed => TestStatus::Passed,
+            Outcome::Errored(..) => TestStatus::Errored,
+            Outcome::Skipped { .. } => TestStatus::Skipped,
+            Outcome::Failed { .. } => TestStatus::Failed,
+            Outcome::Panicked(..) => TestStatus::Panicked,
+            Outcome::Crashed(..) => TestStatus::Crashed,
+            Outcome::ExpectedFailure(..) => TestStatus::ExpectedFailure,
+            Outcome::UnexpectedPass { .. } => TestStatus::UnexpectedPass,
+        }
+    }
+
+    /// Return the message describing the status, such as the reason of failure.
+    pub fn message(&self) -> Option<String> {
+        self.outcome.message()
+    }
+
+    /// Return whether the test case is passed after some failed attempts.
+    pub fn is_flaky(&self) -> bool {
+        match self.outcome {
+            Outcome::Passed => !self.failed_attempts.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Return the path of sections where the test case ended, such as where it failed.
+    pub fn section_path(&self) -> &[String] {
+        &self.section_path
+    }
 }
 
 #[derive(Debug)]
 pub struct Summary {
     pub(crate) passed: Vec<TestCaseSummary>,
+    pub(crate) flaky: Vec<TestCaseSummary>,
     pub(crate) failed: Vec<TestCaseSummary>,
     pub(crate) skipped: Vec<TestCaseSummary>,
+    /// The test cases marked as `xfail` and failed as expected.
+    pub(crate) xfailed: Vec<TestCaseSummary>,
+    /// The test cases marked as `xfail` but passed, which are treated as failures.
+    pub(crate) xpassed: Vec<TestCaseSummary>,
     pub(crate) filtered_out: Vec<&'static TestDesc>,
 }
 
@@ -37,30 +177,135 @@ impl Summary {
     pub(crate) fn empty() -> Self {
         Self {
             passed: vec![],
+            flaky: vec![],
             failed: vec![],
             skipped: vec![],
+            xfailed: vec![],
+            xpassed: vec![],
             filtered_out: vec![],
         }
     }
 
     pub(crate) fn is_passed(&self) -> bool {
-        self.failed.is_empty()
+        self.failed.is_empty() && self.xpassed.is_empty()
+    }
+
+    pub(crate) fn extend(&mut self, other: Summary) {
+        self.passed.extend(other.passed);
+        self.flaky.extend(other.flaky);
+        self.failed.extend(other.failed);
+        self.skipped.extend(other.skipped);
+        self.xfailed.extend(other.xfailed);
+        self.xpassed.extend(other.xpassed);
     }
 
     pub(crate) fn append(&mut self, result: TestCaseSummary) {
         match result.outcome {
+            _ if result.is_flaky() => self.flaky.push(result),
             Outcome::Passed => self.passed.push(result),
-            Outcome::Errored(..) | Outcome::Failed { .. } | Outcome::Panicked(..) => {
-                self.failed.push(result)
-            }
+            Outcome::Errored(..)
+            | Outcome::Failed { .. }
+            | Outcome::Panicked(..)
+            | Outcome::Crashed(..) => self.failed.push(result),
             Outcome::Skipped { .. } => self.skipped.push(result),
+            Outcome::ExpectedFailure(..) => self.xfailed.push(result),
+            Outcome::UnexpectedPass { .. } => self.xpassed.push(result),
+        }
+    }
+}
+
+/// The results of test cases run in a test session.
+///
+/// When the test cases are run repeatedly, the results of all iterations
+/// are collected.
+#[derive(Debug)]
+pub struct Report {
+    pub(crate) summary: Summary,
+    pub(crate) num_iterations: usize,
+    pub(crate) failed_iterations: Vec<usize>,
+}
+
+impl Report {
+    pub(crate) fn empty() -> Self {
+        Self {
+            summary: Summary::empty(),
+            num_iterations: 0,
+            failed_iterations: vec![],
         }
     }
+
+    /// Return whether all test cases have passed.
+    ///
+    /// The expected failures are not counted as failures, but the unexpected
+    /// passes are.
+    pub fn is_passed(&self) -> bool {
+        self.failed_iterations.is_empty()
+    }
+
+    /// Return the results of passed test cases.
+    pub fn passed(&self) -> &[TestCaseSummary] {
+        &self.summary.passed
+    }
+
+    /// Return the results of test cases passed after retrying.
+    pub fn flaky(&self) -> &[TestCaseSummary] {
+        &self.summary.flaky
+    }
+
+    /// Return the results of failed test cases.
+    pub fn failed(&self) -> &[TestCaseSummary] {
+        &self.summary.failed
+    }
+
+    /// Return the results of skipped test cases.
+    pub fn skipped(&self) -> &[TestCaseSummary] {
+        &self.summary.skipped
+    }
+
+    /// Return the results of test cases marked as `xfail` and failed as expected.
+    pub fn xfailed(&self) -> &[TestCaseSummary] {
+        &self.summary.xfailed
+    }
+
+    /// Return the results of test cases marked as `xfail` but passed unexpectedly.
+    pub fn xpassed(&self) -> &[TestCaseSummary] {
+        &self.summary.xpassed
+    }
+
+    /// Return the names of test cases not run due to the filters.
+    pub fn filtered_out(&self) -> impl Iterator<Item = &str> + '_ {
+        self.summary.filtered_out.iter().map(|desc| desc.name())
+    }
+
+    /// Return the number of iterations the test cases were run.
+    pub fn num_iterations(&self) -> usize {
+        self.num_iterations
+    }
+
+    /// Return the iterations where any test case failed, counted from 1.
+    pub fn failed_iterations(&self) -> &[usize] {
+        &self.failed_iterations
+    }
+}
+
+/// The random seeds used in a run of the test cases.
+///
+/// When the test cases are run repeatedly, the seeds are derived from the
+/// session-level ones for each iteration.
+#[derive(Debug, Copy, Clone)]
+pub struct Seeds {
+    /// The seed of random number generators provided to the test cases.
+    pub(crate) seed: u64,
+    /// The seed to shuffle the order of test cases, if they are shuffled.
+    pub(crate) shuffle_seed: Option<u64>,
 }
 
 pub trait Reporter {
-    fn test_run_starting(&self, tests: &[&TestCase]);
-    fn test_run_ended(&self, summary: &Summary);
+    fn test_iteration_starting(&self, iteration: usize);
+    fn test_iterations_ended(&self, num_iterations: usize, failed_iterations: &[(usize, Seeds)]);
+
+    fn test_run_starting(&self, tests: &[&TestCase], seeds: Seeds);
+    fn test_run_ended(&self, summary: &Summary, seeds: Seeds);
 
     fn test_case_starting(&self, desc: &TestDesc);
     fn test_case_ended(&self, summary: &TestCaseSummary);
@@ -68,12 +313,24 @@ pub trait Reporter {
 
 macro_rules! impl_reporter_body {
     () => {
-        fn test_run_starting(&self, tests: &[&TestCase]) {
-            (**self).test_run_starting(tests)
+        fn test_iteration_starting(&self, iteration: usize) {
+            (**self).test_iteration_starting(iteration)
+        }
+
+        fn test_iterations_ended(
+            &self,
+            num_iterations: usize,
+            failed_iterations: &[(usize, Seeds)],
+        ) {
+            (**self).test_iterations_ended(num_iterations, failed_iterations)
+        }
+
+        fn test_run_starting(&self, tests: &[&TestCase], seeds: Seeds) {
+            (**self).test_run_starting(tests, seeds)
         }
 
-        fn test_run_ended(&self, summary: &Summary) {
-            (**self).test_run_ended(summary)
+        fn test_run_ended(&self, summary: &Summary, seeds: Seeds) {
+            (**self).test_run_ended(summary, seeds)
         }
 
         fn test_case_starting(&self, desc: &TestDesc) {
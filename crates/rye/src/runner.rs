@@ -1,6 +1,10 @@
 #![allow(missing_docs)]
 
-use crate::{session::SessionInner, termination::Termination, test::TestCase};
+use crate::{
+    session::{SessionConfig, SessionInner},
+    termination::{Termination, TestResult},
+    test::TestCase,
+};
 use maybe_unwind::capture_panic_info;
 use std::panic;
 use std::sync::Once;
@@ -33,12 +37,24 @@ where
         }));
     });
 
-    let mut session = SessionInner::new(test_cases);
+    let config = match SessionConfig::from_args(std::env::args()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            std::process::exit(101);
+        }
+    };
+
+    let mut session = SessionInner::new(test_cases, config);
     let res = f(&mut session);
 
     let code = match Termination::into_result(res) {
-        Ok(()) => 0,
-        Err(_) => 101,
+        TestResult::Passed | TestResult::Skipped(..) if session.is_failed() => 101,
+        TestResult::Passed | TestResult::Skipped(..) => 0,
+        TestResult::Failed(err) => {
+            eprintln!("error: {:#}", err);
+            101
+        }
     };
     std::process::exit(code);
 }
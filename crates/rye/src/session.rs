@@ -1,62 +1,245 @@
 #![allow(missing_docs)]
 
 use crate::{
-    report::{Outcome, Reporter, Summary, TestCaseSummary},
-    test::{TestCase, TestDesc},
+    filter::{Filter, MatchMode},
+    global::{Global, Globals},
+    isolate,
+    json::JsonReporter,
+    list, random,
+    report::{Outcome, Report, Reporter, Seeds, Summary, TestCaseSummary},
+    snapshot,
+    state::FailedTests,
+    test::{Prepared, TestCase, TestDesc, TestOptions},
+};
+use futures_util::{
+    future,
+    stream::{FuturesUnordered, StreamExt as _},
 };
 use getopts::Options;
+use rand::seq::SliceRandom as _;
 use rye_runtime::Spawner;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     fmt,
     io::{self, Write as _},
+    mem,
     path::Path,
     str::FromStr,
     sync::Arc,
+    task::Poll,
 };
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, StandardStreamLock, WriteColor};
 
-/// Command line arguments.
+/// The configuration of a test session.
+///
+/// The configuration is usually created from the command line arguments
+/// by [`from_args`](#method.from_args), but can also be built with the typed
+/// options for running the test cases programmatically.
 #[derive(Debug)]
-struct Args {
+pub struct SessionConfig {
+    program_name: String,
     show_help: bool,
     list_tests: bool,
-    filter_pattern: Option<String>,
-    filter_exact: bool,
-    color: ColorConfig,
+    format: OutputFormat,
+    ignored: bool,
+    filter_patterns: Vec<String>,
     skip_patterns: Vec<String>,
+    match_mode: MatchMode,
+    color: ColorConfig,
+    keep_temp: bool,
+    update_snapshots: bool,
+    isolate: bool,
+    retries: u32,
+    shuffle: bool,
+    shuffle_seed: Option<u64>,
+    shuffle_sections: bool,
+    seed: Option<u64>,
+    repeat: Option<usize>,
+    until_failure: bool,
+    failed: bool,
+    failed_first: bool,
+    partition: Option<Partition>,
 }
 
-impl Args {
-    fn is_filtered_out(&self, test_name: &str) -> bool {
-        let matches_filter = |pat: &str| {
-            if self.filter_exact {
-                test_name == pat
-            } else {
-                test_name.contains(pat)
-            }
-        };
-
-        if self
-            .filter_pattern
-            .as_ref()
-            .map_or(false, |pat| !matches_filter(pat))
-        {
-            return true;
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            program_name: "rye".into(),
+            show_help: false,
+            list_tests: false,
+            format: OutputFormat::Pretty,
+            ignored: false,
+            filter_patterns: vec![],
+            skip_patterns: vec![],
+            match_mode: MatchMode::Substring,
+            color: ColorConfig::Auto,
+            keep_temp: false,
+            update_snapshots: false,
+            isolate: false,
+            retries: 0,
+            shuffle: false,
+            shuffle_seed: None,
+            shuffle_sections: false,
+            seed: None,
+            repeat: None,
+            until_failure: false,
+            failed: false,
+            failed_first: false,
+            partition: None,
         }
+    }
+}
+
+impl SessionConfig {
+    /// Create a configuration from the command line arguments.
+    ///
+    /// The first item is the program name, as in `std::env::args()`.
+    pub fn from_args<I>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let args: Vec<String> = args.into_iter().map(Into::into).collect();
+        parse_args(&args)
+    }
+
+    /// List the selected test cases instead of running them.
+    pub fn list(&mut self, enabled: bool) -> &mut Self {
+        self.list_tests = enabled;
+        self
+    }
+
+    /// Add a filter to select the test cases or sections to be run.
+    pub fn filter(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.filter_patterns.push(pattern.into());
+        self
+    }
+
+    /// Add a filter to skip the test cases or sections.
+    pub fn skip(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.skip_patterns.push(pattern.into());
+        self
+    }
+
+    /// Specify how the filters are matched against the names of test cases and sections.
+    pub fn match_mode(&mut self, mode: MatchMode) -> &mut Self {
+        self.match_mode = mode;
+        self
+    }
+
+    /// Run only the ignored test cases.
+    ///
+    /// No test cases are ignored in rye, so all test cases are filtered out.
+    pub fn ignored(&mut self, enabled: bool) -> &mut Self {
+        self.ignored = enabled;
+        self
+    }
+
+    /// Configure the format of the test list and the results.
+    pub fn format(&mut self, format: OutputFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Configure coloring of the output.
+    pub fn color(&mut self, color: ColorConfig) -> &mut Self {
+        self.color = color;
+        self
+    }
+
+    /// Keep the temporary directories even if the test cases are passed.
+    pub fn keep_temp(&mut self, enabled: bool) -> &mut Self {
+        self.keep_temp = enabled;
+        self
+    }
+
+    /// Accept the new snapshots instead of failing.
+    pub fn update_snapshots(&mut self, enabled: bool) -> &mut Self {
+        self.update_snapshots = enabled;
+        self
+    }
 
-        if self.skip_patterns.iter().any(|pat| matches_filter(pat)) {
-            return true;
+    /// Run each test case in a separate process.
+    pub fn isolate(&mut self, enabled: bool) -> &mut Self {
+        self.isolate = enabled;
+        self
+    }
+
+    /// Retry failing test cases up to the specified number of times.
+    pub fn retries(&mut self, retries: u32) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Run the test cases in random order using a randomly generated seed.
+    pub fn shuffle(&mut self, enabled: bool) -> &mut Self {
+        self.shuffle = enabled;
+        self
+    }
+
+    /// Run the test cases in random order using the specified seed.
+    pub fn shuffle_seed(&mut self, seed: u64) -> &mut Self {
+        self.shuffle = true;
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Also shuffle the order of sections within each test case.
+    ///
+    /// This implies [`shuffle`](#method.shuffle).
+    pub fn shuffle_sections(&mut self, enabled: bool) -> &mut Self {
+        self.shuffle_sections = enabled;
+        if enabled {
+            self.shuffle = true;
         }
+        self
+    }
+
+    /// Specify the seed of random number generators provided to the test cases.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
 
-        false
+    /// Run the selected test cases the specified number of times.
+    pub fn repeat(&mut self, n: usize) -> &mut Self {
+        self.repeat = Some(n);
+        self
+    }
+
+    /// Run the selected test cases repeatedly until any of them fails.
+    pub fn until_failure(&mut self, enabled: bool) -> &mut Self {
+        self.until_failure = enabled;
+        self
+    }
+
+    /// Run only the test cases and sections failed in the last run.
+    pub fn failed(&mut self, enabled: bool) -> &mut Self {
+        self.failed = enabled;
+        self
+    }
+
+    /// Run the test cases failed in the last run first.
+    pub fn failed_first(&mut self, enabled: bool) -> &mut Self {
+        self.failed_first = enabled;
+        self
+    }
+
+    /// Run only a partition of the test cases.
+    pub fn partition(&mut self, partition: Partition) -> &mut Self {
+        self.partition = Some(partition);
+        self
     }
 }
 
+/// Whether to colorize the output.
 #[derive(Copy, Clone, Debug, PartialEq)]
-enum ColorConfig {
+pub enum ColorConfig {
+    /// Colorize if the output is a terminal.
     Auto,
+    /// Always colorize the output.
     Always,
+    /// Never colorize the output.
     Never,
 }
 
@@ -76,94 +259,336 @@ impl FromStr for ColorConfig {
     }
 }
 
-struct Parser {
-    args: Vec<String>,
-    opts: Options,
+/// The format of the test list and the results.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// The human-readable output.
+    Pretty,
+    /// `name: test` lines only, as printed by the built-in test harness.
+    Terse,
+    /// A JSON object per line with the details of each test case or event.
+    Json,
 }
 
-impl Parser {
-    fn new(args: impl IntoIterator<Item = String>) -> Self {
-        let mut opts = Options::new();
-        opts.optflag("h", "help", "Display this message (longer with --help)");
-        opts.optflag("", "list", "List all tests and benchmarks");
-        opts.optflag(
-            "",
-            "exact",
-            "Exactly match filters rather than by substring",
-        );
-        opts.optopt(
-            "",
-            "color",
-            "Configure coloring of output:
-                auto   = colorize if stdout is a tty and tests are run on serially (default);
-                always = always colorize output;
-                never  = never colorize output;",
-            "auto|always|never",
-        );
-        opts.optmulti(
-            "",
-            "skip",
-            "Skip tests whose names contain FILTER (this flag can be used multiple times)",
-            "FILTER",
-        );
-
-        // The following options and flags are reserved for keeping the compatibility with
-        // the built-in test harness.
-        opts.optflag("", "ignored", "");
-        opts.optflag("", "test", "");
-        opts.optflag("", "bench", "");
-        opts.optflag("", "nocapture", "");
-        opts.optflag("q", "quiet", "");
-        opts.optopt("", "logfile", "", "PATH");
-        opts.optopt("", "test-threads", "", "n_threads");
-        opts.optopt("", "format", "", "");
-        opts.optopt("Z", "", "", "unstable-options");
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
 
-        Self {
-            args: args.into_iter().collect(),
-            opts,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "terse" => Ok(OutputFormat::Terse),
+            "json" => Ok(OutputFormat::Json),
+            v => Err(anyhow::anyhow!(
+                "argument for --format must be pretty, terse, or json (was {})",
+                v
+            )),
         }
     }
+}
 
-    fn print_usage(&self) {
-        let binary = &self.args[0];
-        let progname = Path::new(binary)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or(binary);
+/// One of the disjoint subsets of the test cases, written as `hash:K/N` or
+/// `count:K/N` in the command line arguments.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Partition {
+    strategy: PartitionStrategy,
+    index: u64,
+    count: u64,
+}
 
-        let message = format!("Usage: {} [OPTIONS] [FILTER]", progname);
-        eprintln!(
-            r#"{usage}
-The FILTER string is tested against the name of all tests, and only those
-tests whose names contain the filter are run."#,
-            usage = self.opts.usage(&message)
+/// The strategy to split the test cases into disjoint subsets.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PartitionStrategy {
+    /// Select the test cases by the hash of their names.
+    Hash,
+    /// Select the test cases in round-robin order of the sorted names.
+    Count,
+}
+
+impl Partition {
+    /// Create the `index`-th of `count` partitions, selecting the test cases
+    /// by the hash of their names.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is zero or greater than `count`.
+    pub fn hash(index: u64, count: u64) -> Self {
+        Self::new(PartitionStrategy::Hash, index, count)
+    }
+
+    /// Create the `index`-th of `count` partitions, selecting the test cases
+    /// in round-robin order of their sorted names.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is zero or greater than `count`.
+    pub fn count(index: u64, count: u64) -> Self {
+        Self::new(PartitionStrategy::Count, index, count)
+    }
+
+    fn new(strategy: PartitionStrategy, index: u64, count: u64) -> Self {
+        assert!(
+            (1..=count).contains(&index),
+            "the partition index must satisfy 1 <= K <= N (was {}/{})",
+            index,
+            count
         );
+        Self {
+            strategy,
+            index,
+            count,
+        }
     }
 
-    fn parse(&self) -> anyhow::Result<Args> {
-        let args = &self.args[..];
+    /// Return whether the `i`-th test case in the sorted list belongs to this partition.
+    fn contains(&self, i: usize, test_name: &str) -> bool {
+        let n = match self.strategy {
+            PartitionStrategy::Hash => random::hash(0, test_name.as_bytes()),
+            PartitionStrategy::Count => i as u64,
+        };
+        n % self.count == self.index - 1
+    }
+}
 
-        let matches = self.opts.parse(args.get(1..).unwrap_or(args))?;
+impl FromStr for Partition {
+    type Err = anyhow::Error;
 
-        let show_help = matches.opt_present("help");
-        let list_tests = matches.opt_present("list");
-        let filter_exact = matches.opt_present("exact");
-        let color = matches.opt_get("color")?.unwrap_or(ColorConfig::Auto);
-        let skip_patterns = matches.opt_strs("skip");
-        let filter_pattern = matches.free.get(0).cloned();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "argument for --partition must be hash:K/N or count:K/N with 1 <= K <= N (was {})",
+                s
+            )
+        };
+
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().ok_or_else(invalid)?;
+        let mut range = parts.next().ok_or_else(invalid)?.splitn(2, '/');
+        let index: u64 = range
+            .next()
+            .and_then(|k| k.parse().ok())
+            .ok_or_else(invalid)?;
+        let count: u64 = range
+            .next()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(invalid)?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
 
-        Ok(Args {
-            show_help,
-            list_tests,
-            filter_pattern,
-            filter_exact,
-            color,
-            skip_patterns,
-        })
+        match kind {
+            "hash" => Ok(Partition::hash(index, count)),
+            "count" => Ok(Partition::count(index, count)),
+            _ => Err(invalid()),
+        }
     }
 }
 
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "Display this message (longer with --help)");
+    opts.optflag("", "list", "List all tests and benchmarks");
+    opts.optopt(
+        "",
+        "format",
+        "Configure the format of output:
+                pretty = human-readable output (default);
+                terse  = the names of tests only (with --list);
+                json   = a JSON object per test with its location, kind and sections
+                         (with --list), or per event of the test run;",
+        "pretty|terse|json",
+    );
+    opts.optflag(
+        "",
+        "exact",
+        "Exactly match filters rather than by substring",
+    );
+    opts.optflag(
+        "",
+        "regex",
+        "Interpret filters as regular expressions rather than substrings",
+    );
+    opts.optopt(
+        "",
+        "color",
+        "Configure coloring of output:
+                auto   = colorize if stdout is a tty and tests are run on serially (default);
+                always = always colorize output;
+                never  = never colorize output;",
+        "auto|always|never",
+    );
+    opts.optmulti(
+        "",
+        "skip",
+        "Skip tests or sections matching FILTER (this flag can be used multiple times)",
+        "FILTER",
+    );
+    opts.optflag(
+        "",
+        "keep-temp",
+        "Keep the temporary directories created by test cases even if they are passed",
+    );
+    opts.optflag(
+        "",
+        "update-snapshots",
+        "Accept the new snapshots instead of failing (same as RYE_UPDATE_SNAPSHOTS=1)",
+    );
+    opts.optflag("", "isolate", "Run each test case in a separate process");
+    opts.optopt(
+        "",
+        "retries",
+        "Retry failing test cases up to N times and report them as flaky if passed",
+        "N",
+    );
+    opts.optflag("", "shuffle", "Run the test cases in random order");
+    opts.optopt(
+        "",
+        "shuffle-seed",
+        "Run the test cases in random order using the specified seed (implies --shuffle)",
+        "SEED",
+    );
+    opts.optflag(
+        "",
+        "shuffle-sections",
+        "Also shuffle the order of sections within each test case (implies --shuffle)",
+    );
+    opts.optopt(
+        "",
+        "seed",
+        "Specify the seed of random number generators provided to the test cases \
+         (same as RYE_SEED=SEED)",
+        "SEED",
+    );
+    opts.optopt(
+        "",
+        "repeat",
+        "Run the selected test cases N times repeatedly",
+        "N",
+    );
+    opts.optflag(
+        "",
+        "until-failure",
+        "Run the selected test cases repeatedly until any of them fails",
+    );
+    opts.optflag(
+        "",
+        "failed",
+        "Run only the test cases and sections failed in the last run",
+    );
+    opts.optflag(
+        "",
+        "failed-first",
+        "Run the test cases failed in the last run first, and then the others",
+    );
+    opts.optopt(
+        "",
+        "partition",
+        "Run only the K-th of N disjoint subsets of the test cases, selected by \
+         the hash of their names or in round-robin order",
+        "hash:K/N|count:K/N",
+    );
+
+    // The following options and flags are reserved for keeping the compatibility with
+    // the built-in test harness.
+    opts.optflag("", "ignored", ""); // no test cases are ignored in rye
+    opts.optflag("", "test", "");
+    opts.optflag("", "bench", "");
+    opts.optflag("", "nocapture", "");
+    opts.optflag("q", "quiet", "");
+    opts.optopt("", "logfile", "", "PATH");
+    opts.optopt("", "test-threads", "", "n_threads");
+    opts.optopt("Z", "", "", "unstable-options");
+
+    opts
+}
+
+fn print_usage(program_name: &str) {
+    let progname = Path::new(program_name)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(program_name);
+
+    let message = format!("Usage: {} [OPTIONS] [FILTER]...", progname);
+    eprintln!(
+        r#"{usage}
+The FILTER strings are tested against the name of all tests and the paths of
+their sections (written as `test_name/section/nested section`), and only those
+tests or sections that match any of the filters are run. Unless --exact or
+--regex is specified, a filter containing `*` or `?` is treated as a glob
+pattern matching the whole name or path."#,
+        usage = options().usage(&message)
+    );
+}
+
+fn parse_args(args: &[String]) -> anyhow::Result<SessionConfig> {
+    let program_name = args.get(0).cloned().unwrap_or_else(|| "rye".into());
+    let matches = options().parse(args.get(1..).unwrap_or(args))?;
+
+    let show_help = matches.opt_present("help");
+    let list_tests = matches.opt_present("list");
+    let format = matches.opt_get("format")?.unwrap_or(OutputFormat::Pretty);
+    let ignored = matches.opt_present("ignored");
+    let color = matches.opt_get("color")?.unwrap_or(ColorConfig::Auto);
+    anyhow::ensure!(
+        !(matches.opt_present("exact") && matches.opt_present("regex")),
+        "--exact and --regex cannot be specified at the same time"
+    );
+    let match_mode = if matches.opt_present("exact") {
+        MatchMode::Exact
+    } else if matches.opt_present("regex") {
+        MatchMode::Regex
+    } else {
+        MatchMode::Substring
+    };
+    let filter_patterns = matches.free.clone();
+    let skip_patterns = matches.opt_strs("skip");
+    let keep_temp = matches.opt_present("keep-temp");
+    let update_snapshots =
+        matches.opt_present("update-snapshots") || snapshot::update_snapshots_from_env();
+    let isolate = matches.opt_present("isolate");
+    let retries = matches.opt_get_default("retries", 0)?;
+    let shuffle_seed = matches.opt_get("shuffle-seed")?;
+    let shuffle_sections = matches.opt_present("shuffle-sections");
+    let shuffle = matches.opt_present("shuffle") || shuffle_seed.is_some() || shuffle_sections;
+    let seed = match matches.opt_get("seed")? {
+        Some(seed) => Some(seed),
+        None => random::seed_from_env()?,
+    };
+    let repeat = matches.opt_get("repeat")?;
+    anyhow::ensure!(
+        repeat.map_or(true, |n| n > 0),
+        "argument for --repeat must be a positive integer"
+    );
+    let until_failure = matches.opt_present("until-failure");
+    let failed = matches.opt_present("failed");
+    let failed_first = matches.opt_present("failed-first");
+    let partition = matches.opt_get("partition")?;
+
+    Ok(SessionConfig {
+        program_name,
+        show_help,
+        list_tests,
+        format,
+        ignored,
+        filter_patterns,
+        skip_patterns,
+        match_mode,
+        color,
+        keep_temp,
+        update_snapshots,
+        isolate,
+        retries,
+        shuffle,
+        shuffle_seed,
+        shuffle_sections,
+        seed,
+        repeat,
+        until_failure,
+        failed,
+        failed_first,
+        partition,
+    })
+}
+
 struct Colored<T> {
     val: T,
     spec: Option<ColorSpec>,
@@ -215,22 +640,112 @@ impl ConsoleReporter {
     ) -> io::Result<()> {
         let status = match summary.outcome {
             Outcome::Passed => colored("ok").fg(Color::Green),
-            Outcome::Errored(..) | Outcome::Failed { .. } | Outcome::Panicked(..) => {
-                colored("FAILED").fg(Color::Red)
-            }
+            Outcome::Errored(..)
+            | Outcome::Failed { .. }
+            | Outcome::Panicked(..)
+            | Outcome::Crashed(..) => colored("FAILED").fg(Color::Red),
             Outcome::Skipped { .. } => colored("skipped").fg(Color::Yellow),
+            Outcome::ExpectedFailure(..) => colored("expected failure").fg(Color::Yellow),
+            Outcome::UnexpectedPass { .. } => colored("unexpected pass").fg(Color::Red),
         };
         write!(w, "test {} ... ", summary.desc.name(),)?;
         status.fmt_colored(w)?;
+        if summary.is_flaky() {
+            write!(w, " ")?;
+            colored("(flaky)").fg(Color::Yellow).fmt_colored(w)?;
+        }
         writeln!(w)?;
         Ok(())
     }
 
-    fn print_summary(&self, w: &mut StandardStreamLock<'_>, summary: &Summary) -> io::Result<()> {
-        if !summary.failed.is_empty() {
+    fn print_outcome(&self, w: &mut StandardStreamLock<'_>, outcome: &Outcome) -> io::Result<()> {
+        match outcome {
+            Outcome::Errored(ref err) => {
+                writeln!(w, "{:?}", err)?;
+            }
+            Outcome::Panicked(ref message) => {
+                writeln!(w, "{}", message)?;
+            }
+            Outcome::Crashed(ref message) => {
+                writeln!(w, "{}", message)?;
+            }
+            Outcome::Failed {
+                ref location,
+                ref reason,
+                ref diff,
+            } => {
+                writeln!(w, "{} {}", location, reason)?;
+                if let Some(diff) = diff {
+                    diff.write_colored(w)?;
+                    writeln!(w)?;
+                }
+            }
+            Outcome::ExpectedFailure(ref outcome) => {
+                self.print_outcome(w, outcome)?;
+            }
+            Outcome::UnexpectedPass { reason } => {
+                writeln!(
+                    w,
+                    "the test case is expected to fail ({}), but passed",
+                    reason
+                )?;
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn print_failed_attempts(
+        &self,
+        w: &mut StandardStreamLock<'_>,
+        result: &TestCaseSummary,
+    ) -> io::Result<()> {
+        for (i, outcome) in result.failed_attempts.iter().enumerate() {
+            writeln!(w, "[attempt {}]", i + 1)?;
+            self.print_outcome(w, outcome)?;
+        }
+        Ok(())
+    }
+
+    fn print_summary(
+        &self,
+        w: &mut StandardStreamLock<'_>,
+        summary: &Summary,
+        seeds: Seeds,
+    ) -> io::Result<()> {
+        if !summary.flaky.is_empty() {
+            writeln!(w)?;
+            writeln!(w, "flaky tests:")?;
+            for result in &summary.flaky {
+                writeln!(
+                    w,
+                    "---- {} at {} (passed after {} attempts) ----",
+                    result.desc.name(),
+                    result.desc.location,
+                    result.failed_attempts.len() + 1,
+                )?;
+                self.print_failed_attempts(w, result)?;
+                writeln!(w)?;
+            }
+        }
+
+        if !summary.xfailed.is_empty() {
+            writeln!(w)?;
+            writeln!(w, "expected failures:")?;
+            for result in &summary.xfailed {
+                writeln!(
+                    w,
+                    "    {} ({})",
+                    result.desc.name(),
+                    result.desc.xfail.unwrap_or_default()
+                )?;
+            }
+        }
+
+        if !summary.is_passed() {
             writeln!(w)?;
             writeln!(w, "failures:")?;
-            for result in &summary.failed {
+            for result in summary.failed.iter().chain(&summary.xpassed) {
                 writeln!(
                     w,
                     "---- {} at {} ----",
@@ -238,29 +753,26 @@ impl ConsoleReporter {
                     result.desc.location
                 )?;
 
-                match result.outcome {
-                    Outcome::Errored(ref err) => {
-                        writeln!(w, "{:?}", err)?;
-                    }
-                    Outcome::Panicked(ref unwind) => {
-                        writeln!(w, "{:#}", unwind)?;
-                    }
-                    Outcome::Failed {
-                        ref location,
-                        ref reason,
-                    } => {
-                        writeln!(w, "{} {}", location, reason)?;
-                    }
-                    _ => unreachable!(),
+                self.print_failed_attempts(w, result)?;
+                if !result.failed_attempts.is_empty() {
+                    writeln!(w, "[attempt {}]", result.failed_attempts.len() + 1)?;
                 }
+                self.print_outcome(w, &result.outcome)?;
                 writeln!(w)?;
             }
 
             writeln!(w)?;
             writeln!(w, "failures:")?;
-            for result in &summary.failed {
+            for result in summary.failed.iter().chain(&summary.xpassed) {
                 writeln!(w, "    {}", result.desc.name())?;
             }
+
+            writeln!(w)?;
+            writeln!(
+                w,
+                "random seed: {} (rerun with --seed={} to reproduce the failures)",
+                seeds.seed, seeds.seed
+            )?;
         }
 
         let status = if summary.is_passed() {
@@ -274,26 +786,94 @@ impl ConsoleReporter {
         write!(w, ".")?;
         writeln!(
             w,
-            " {passed} passed; {failed} failed; {skipped} skipped; {filtered_out} filtered out",
+            " {passed} passed; {flaky} flaky; {failed} failed; {skipped} skipped; {xfailed} xfailed; {xpassed} xpassed; {filtered_out} filtered out",
             passed = summary.passed.len(),
+            flaky = summary.flaky.len(),
             failed = summary.failed.len(),
             skipped = summary.skipped.len(),
+            xfailed = summary.xfailed.len(),
+            xpassed = summary.xpassed.len(),
             filtered_out = summary.filtered_out.len(),
         )?;
+        if let Some(seed) = seeds.shuffle_seed {
+            writeln!(
+                w,
+                "shuffle seed: {} (rerun with --shuffle-seed={} to reproduce the order)",
+                seed, seed
+            )?;
+        }
 
         Ok(())
     }
+
+    fn print_iterations_summary(
+        &self,
+        w: &mut StandardStreamLock<'_>,
+        num_iterations: usize,
+        failed_iterations: &[(usize, Seeds)],
+    ) -> io::Result<()> {
+        let status = if failed_iterations.is_empty() {
+            colored("ok").fg(Color::Green)
+        } else {
+            colored("FAILED").fg(Color::Red)
+        };
+        writeln!(w)?;
+        write!(w, "repeat result: ")?;
+        status.fmt_colored(w)?;
+        writeln!(
+            w,
+            ". {iterations} iterations; {passed} passed; {failed} failed",
+            iterations = num_iterations,
+            passed = num_iterations - failed_iterations.len(),
+            failed = failed_iterations.len(),
+        )?;
+        if !failed_iterations.is_empty() {
+            writeln!(w, "failed iterations:")?;
+            for &(iteration, seeds) in failed_iterations {
+                write!(
+                    w,
+                    "    iteration {} (rerun with --seed={}",
+                    iteration, seeds.seed
+                )?;
+                if let Some(seed) = seeds.shuffle_seed {
+                    write!(w, " --shuffle-seed={}", seed)?;
+                }
+                writeln!(w, " to reproduce)")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Reporter for ConsoleReporter {
-    fn test_run_starting(&self, tests: &[&TestCase]) {
+    fn test_iteration_starting(&self, iteration: usize) {
+        let mut w = self.stream.lock();
+        if iteration > 1 {
+            let _ = writeln!(w);
+        }
+        let _ = writeln!(w, "iteration {}", iteration);
+    }
+
+    fn test_iterations_ended(&self, num_iterations: usize, failed_iterations: &[(usize, Seeds)]) {
+        let mut w = self.stream.lock();
+        let _ = self.print_iterations_summary(&mut w, num_iterations, failed_iterations);
+    }
+
+    fn test_run_starting(&self, tests: &[&TestCase], seeds: Seeds) {
         let mut w = self.stream.lock();
-        let _ = writeln!(w, "running {} tests", tests.len());
+        match seeds.shuffle_seed {
+            Some(seed) => {
+                let _ = writeln!(w, "running {} tests (shuffle seed: {})", tests.len(), seed);
+            }
+            None => {
+                let _ = writeln!(w, "running {} tests", tests.len());
+            }
+        }
     }
 
-    fn test_run_ended(&self, summary: &Summary) {
+    fn test_run_ended(&self, summary: &Summary, seeds: Seeds) {
         let mut w = self.stream.lock();
-        let _ = self.print_summary(&mut w, summary);
+        let _ = self.print_summary(&mut w, summary, seeds);
     }
 
     fn test_case_starting(&self, _: &TestDesc) {}
@@ -305,42 +885,106 @@ impl Reporter for ConsoleReporter {
 }
 
 pub struct SessionInner<'a> {
-    parser: Parser,
+    config: SessionConfig,
     test_cases: &'a [&'static TestCase],
+    failed: bool,
 }
 
 impl<'a> SessionInner<'a> {
     #[inline]
-    pub(crate) fn new(test_cases: &'a [&'static TestCase]) -> Self {
+    pub(crate) fn new(test_cases: &'a [&'static TestCase], config: SessionConfig) -> Self {
         Self {
-            parser: Parser::new(std::env::args()),
+            config,
             test_cases,
+            failed: false,
         }
     }
 
+    /// Return whether any test session run on this value has failed.
+    pub(crate) fn is_failed(&self) -> bool {
+        self.failed
+    }
+
     #[inline]
     pub fn session<'sess>(&'sess mut self, spawner: &'sess mut dyn Spawner) -> Session<'sess> {
         Session {
-            parser: &mut self.parser,
+            config: &self.config,
             test_cases: self.test_cases,
             spawner,
+            globals: Globals::default(),
+            failed: Some(&mut self.failed),
         }
     }
 }
 
 pub struct Session<'sess> {
-    parser: &'sess mut Parser,
+    config: &'sess SessionConfig,
     test_cases: &'sess [&'static TestCase],
     spawner: &'sess mut dyn Spawner,
+    globals: Globals,
+    failed: Option<&'sess mut bool>,
+}
+
+impl<'sess> Session<'sess> {
+    /// Create a test session that runs the specified test cases.
+    ///
+    /// The test cases are the constants defined by `#[rye::test]`.
+    pub fn new(
+        test_cases: &'sess [&'static TestCase],
+        spawner: &'sess mut dyn Spawner,
+        config: &'sess SessionConfig,
+    ) -> Self {
+        Self {
+            config,
+            test_cases,
+            spawner,
+            globals: Globals::default(),
+            failed: None,
+        }
+    }
 }
 
 impl Session<'_> {
-    #[inline]
-    pub async fn run(&mut self) -> anyhow::Result<()> {
-        let args = self.parser.parse()?;
+    /// Register a global value shared among the test cases in this session.
+    ///
+    /// If a global value of the same type has already been registered,
+    /// it is replaced with the new one.
+    pub fn register_global<T>(&mut self, global: Global<T>) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.globals.register(global);
+        self
+    }
+
+    /// Run the test cases and return the report of their results.
+    ///
+    /// The failures of test cases are reported through the returned `Report`,
+    /// and an error is returned only if the session itself could not be run.
+    pub async fn run(&mut self) -> anyhow::Result<Report> {
+        let args = self.config;
         if args.show_help {
-            self.parser.print_usage();
-            return Ok(());
+            print_usage(&args.program_name);
+            return Ok(Report::empty());
+        }
+
+        if args.format == OutputFormat::Terse && !args.list_tests {
+            anyhow::bail!("--format=terse is supported only with --list");
+        }
+
+        let mut filter = Filter::new(&args.filter_patterns, &args.skip_patterns, args.match_mode)?;
+
+        let mut failed_tests = if isolate::is_child() {
+            FailedTests::default()
+        } else {
+            FailedTests::load()
+        };
+        if args.failed {
+            if failed_tests.is_empty() {
+                eprintln!("warning: no failed test cases are recorded in the last run; running all test cases");
+            } else {
+                filter.select_paths(failed_tests.paths());
+            }
         }
 
         let mut registered_tests = vec![];
@@ -348,7 +992,7 @@ impl Session<'_> {
         let mut unique_test_names = HashSet::new();
         for test in self.test_cases {
             let desc = &test.desc;
-            let filtered_out = args.is_filtered_out(desc.name());
+            let filtered_out = args.ignored || filter.select_plans(test).is_empty();
 
             anyhow::ensure!(
                 unique_test_names.insert(desc.name().to_owned()),
@@ -363,58 +1007,545 @@ impl Session<'_> {
             }
         }
 
-        // sort test cases by name.
+        // sort test cases by name, and then shuffle them if requested.
         registered_tests.sort_by(|t1, t2| t1.desc.name().cmp(t2.desc.name()));
+        if let Some(partition) = args.partition {
+            let mut i = 0;
+            registered_tests.retain(|test| {
+                let contained = partition.contains(i, test.desc.name());
+                if !contained {
+                    filtered_out_tests.push(&test.desc);
+                }
+                i += 1;
+                contained
+            });
+        }
+        let shuffle_seed = if args.shuffle {
+            Some(args.shuffle_seed.unwrap_or_else(random::generate_seed))
+        } else {
+            None
+        };
+        // The order of test cases is determined for each iteration.
+        let arrange = |tests: &mut Vec<&'static TestCase>, shuffle_seed: Option<u64>| {
+            if let Some(seed) = shuffle_seed {
+                shuffle_tests(tests, seed);
+            }
+            if args.failed_first {
+                tests.sort_by_key(|test| !failed_tests.contains(test.desc.name()));
+            }
+        };
 
         if args.list_tests {
-            let mut num_tests = 0;
-            for test in &registered_tests {
-                num_tests += 1;
-                println!("{}: test", test.desc.name());
+            let mut tests = registered_tests.clone();
+            arrange(&mut tests, shuffle_seed);
+            list::print_list(&tests, args.format)?;
+            return Ok(Report::empty());
+        }
+
+        let max_iterations = match args.repeat {
+            Some(n) => Some(n),
+            None if args.until_failure => None,
+            None => Some(1),
+        };
+        let repeating = max_iterations.map_or(true, |n| n > 1);
+
+        let seed = args.seed.unwrap_or_else(random::generate_seed);
+        let reporter: Arc<dyn Reporter + Send + Sync> = if isolate::is_child() {
+            Arc::new(isolate::ChildReporter::new()?)
+        } else {
+            match args.format {
+                OutputFormat::Json => Arc::new(JsonReporter::new(io::stdout())),
+                _ => Arc::new(ConsoleReporter::new(match args.color {
+                    ColorConfig::Auto => ColorChoice::Auto,
+                    ColorConfig::Always => ColorChoice::Always,
+                    ColorConfig::Never => ColorChoice::Never,
+                })),
+            }
+        };
+
+        let globals = mem::take(&mut self.globals);
+
+        let mut report = Report::empty();
+        report
+            .summary
+            .filtered_out
+            .extend(filtered_out_tests.iter().copied());
+
+        let mut iteration = 0;
+        let mut failed_iterations = vec![];
+        let mut failed_sections = HashMap::new();
+        while max_iterations.map_or(true, |n| iteration < n) {
+            iteration += 1;
+            if repeating {
+                reporter.test_iteration_starting(iteration);
+            }
+
+            // Each iteration uses the different seeds, derived from the session-level
+            // ones, so that a failing iteration can be reproduced alone.
+            let seeds = Seeds {
+                seed: random::derive_seed(seed, iteration),
+                shuffle_seed: shuffle_seed.map(|seed| random::derive_seed(seed, iteration)),
+            };
+            let mut tests = registered_tests.clone();
+            arrange(&mut tests, seeds.shuffle_seed);
+            let options = TestOptions {
+                keep_temp: args.keep_temp,
+                update_snapshots: args.update_snapshots,
+                isolate: args.isolate,
+                is_child: isolate::is_child(),
+                retries: args.retries,
+                shuffle_seed: if args.shuffle_sections {
+                    seeds.shuffle_seed
+                } else {
+                    None
+                },
+                seed: seeds.seed,
+            };
+
+            reporter.test_run_starting(&tests, seeds);
+            let summary = match self
+                .run_iteration(
+                    &tests,
+                    &filtered_out_tests,
+                    &reporter,
+                    &globals,
+                    &filter,
+                    options,
+                )
+                .await
+            {
+                Ok(summary) => summary,
+                Err(err) => {
+                    // the global values must be torn down even if the session is aborted.
+                    for err in globals.teardown().await {
+                        eprintln!("warning: {}", err);
+                    }
+                    return Err(err);
+                }
+            };
+            reporter.test_run_ended(&summary, seeds);
+
+            for result in summary.failed.iter().chain(&summary.xpassed) {
+                failed_sections
+                    .entry(result.desc.name())
+                    .or_insert_with(|| result.section_path.clone());
             }
 
-            fn plural_suffix(n: usize) -> &'static str {
-                match n {
-                    1 => "",
-                    _ => "s",
+            let passed = summary.is_passed();
+            report.summary.extend(summary);
+
+            if !passed {
+                failed_iterations.push((iteration, seeds));
+                if args.until_failure {
+                    break;
                 }
             }
+        }
+
+        if repeating {
+            reporter.test_iterations_ended(iteration, &failed_iterations);
+        }
 
-            if num_tests != 0 {
-                println!();
+        for err in globals.teardown().await {
+            eprintln!("warning: {}", err);
+        }
+
+        if !isolate::is_child() {
+            for test in &registered_tests {
+                let name = test.desc.name();
+                failed_tests.update(name, failed_sections.get(name).map(Vec::as_slice));
             }
-            println!("{} test{}", num_tests, plural_suffix(num_tests));
+            if let Err(err) = failed_tests.save() {
+                eprintln!("warning: failed to save the failed test cases: {}", err);
+            }
+        }
 
-            return Ok(());
+        report.num_iterations = iteration;
+        report.failed_iterations = failed_iterations.iter().map(|&(i, _)| i).collect();
+        if let Some(ref mut failed) = self.failed {
+            **failed |= !report.is_passed();
         }
 
-        let reporter = Arc::new(ConsoleReporter::new(match args.color {
-            ColorConfig::Auto => ColorChoice::Auto,
-            ColorConfig::Always => ColorChoice::Always,
-            ColorConfig::Never => ColorChoice::Never,
-        }));
+        Ok(report)
+    }
 
-        reporter.test_run_starting(&registered_tests[..]);
+    /// Run the registered test cases once and report their results.
+    async fn run_iteration(
+        &mut self,
+        tests: &[&'static TestCase],
+        filtered_out_tests: &[&'static TestDesc],
+        reporter: &Arc<dyn Reporter + Send + Sync>,
+        globals: &Globals,
+        filter: &Filter,
+        options: TestOptions,
+    ) -> anyhow::Result<Summary> {
+        enum Event {
+            Prepared(Prepared),
+            Completed(TestCaseSummary),
+        }
 
         let mut summary = Summary::empty();
         summary.filtered_out.extend(filtered_out_tests);
-        let mut handles = vec![];
-        for test in registered_tests.drain(..) {
-            let reporter = reporter.clone();
-            let handle = test.spawn(&mut *self.spawner, reporter)?;
-            handles.push(handle);
+
+        // Spawn the test cases as long as their resources are not locked by
+        // the running test cases, so that the test cases sharing a resource
+        // are never run concurrently.
+        //
+        // The global values are acquired concurrently before spawning, so
+        // that a slow initialization only delays the test cases requesting it.
+        let mut pending: VecDeque<_> = tests.iter().copied().collect();
+        let mut preparing = FuturesUnordered::new();
+        let mut running = FuturesUnordered::new();
+        let mut locked_resources = HashSet::new();
+        let mut results = vec![];
+        loop {
+            let mut i = 0;
+            while i < pending.len() {
+                let resources = pending[i].desc.resources;
+                if resources.iter().any(|r| locked_resources.contains(r)) {
+                    i += 1;
+                    continue;
+                }
+                locked_resources.extend(resources.iter().copied());
+
+                let test = pending.remove(i).unwrap();
+                preparing.push(test.prepare(globals, filter, options));
+            }
+
+            if preparing.is_empty() && running.is_empty() {
+                break;
+            }
+
+            let event = future::poll_fn(|cx| {
+                if let Poll::Ready(Some(prepared)) = preparing.poll_next_unpin(cx) {
+                    return Poll::Ready(Event::Prepared(prepared));
+                }
+                if let Poll::Ready(Some(result)) = running.poll_next_unpin(cx) {
+                    return Poll::Ready(Event::Completed(result));
+                }
+                Poll::Pending
+            })
+            .await;
+
+            match event {
+                Event::Prepared(prepared) => {
+                    let handle = prepared.spawn(&mut *self.spawner, reporter.clone())?;
+                    running.push(handle);
+                }
+                Event::Completed(result) => {
+                    for resource in result.desc.resources {
+                        locked_resources.remove(resource);
+                    }
+                    results.push(result);
+                }
+            }
         }
-        let results = futures_util::future::join_all(handles).await;
+
+        results.sort_by(|r1, r2| r1.desc.name().cmp(r2.desc.name()));
         for result in results {
             summary.append(result);
         }
 
-        reporter.test_run_ended(&summary);
+        Ok(summary)
+    }
+}
 
-        if summary.is_passed() {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(""))
+/// Shuffle the test cases sorted by name, in the order determined by the seed.
+fn shuffle_tests(tests: &mut [&'static TestCase], seed: u64) {
+    tests.shuffle(&mut random::rng(seed, ""));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn shuffled_tests() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test1(_: &mut crate::Context<'_>) {}
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test2(_: &mut crate::Context<'_>) {}
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test3(_: &mut crate::Context<'_>) {}
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test4(_: &mut crate::Context<'_>) {}
+
+        let shuffled = |seed| {
+            let mut tests = vec![test1, test2, test3, test4];
+            shuffle_tests(&mut tests, seed);
+            tests
+                .iter()
+                .map(|test| test.desc.name())
+                .collect::<Vec<_>>()
+        };
+
+        let names = shuffled(1);
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            vec![
+                test1.desc.name(),
+                test2.desc.name(),
+                test3.desc.name(),
+                test4.desc.name()
+            ]
+        );
+
+        // the order is determined only by the seed.
+        assert_eq!(shuffled(1), names);
+        assert_ne!(shuffled(2), names);
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn slow_global_does_not_block_other_tests() {
+        use futures::future::poll_fn;
+        use rye_runtime::{default_runtime, Runtime as _};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        static OPENED: AtomicBool = AtomicBool::new(false);
+
+        struct Gate;
+
+        // The initialization of `Gate` is completed only after `test2` is run.
+        #[crate::test]
+        #[rye(crate = crate)]
+        #[rye(global = Gate)]
+        fn test1(_: &mut crate::Context<'_>) {}
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test2(_: &mut crate::Context<'_>) {
+            OPENED.store(true, Ordering::SeqCst);
+        }
+
+        let tests = vec![test1, test2];
+        let config = SessionConfig::default();
+        let mut rt = default_runtime();
+        let mut spawner = rt.spawner();
+        let report = rt
+            .block_on(async {
+                let mut sess = Session::new(&tests, &mut spawner, &config);
+                sess.register_global(Global::new(|| async {
+                    poll_fn(|cx| {
+                        if OPENED.load(Ordering::SeqCst) {
+                            Poll::Ready(())
+                        } else {
+                            cx.waker().wake_by_ref();
+                            Poll::Pending
+                        }
+                    })
+                    .await;
+                    Ok(Gate)
+                }));
+                sess.run().await
+            })
+            .unwrap();
+        assert!(report.is_passed());
+        assert_eq!(report.passed().len(), 2);
+    }
+
+    #[cfg(not(frameworks))]
+    #[test]
+    fn serial_group_is_not_run_concurrently() {
+        use futures::future::poll_fn;
+        use rye_runtime::{default_runtime, Runtime as _};
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        static RUNNING_IN_GROUP: AtomicUsize = AtomicUsize::new(0);
+        static GROUP_STARTED: AtomicBool = AtomicBool::new(false);
+        static OTHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+        async fn wait_for(flag: &'static AtomicBool) {
+            poll_fn(|cx| {
+                if flag.load(Ordering::SeqCst) {
+                    Poll::Ready(())
+                } else {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+
+        async fn run_in_group() {
+            assert_eq!(RUNNING_IN_GROUP.fetch_add(1, Ordering::SeqCst), 0);
+            GROUP_STARTED.store(true, Ordering::SeqCst);
+            // the unrelated test case must be able to start while a test case
+            // in the group is running.
+            wait_for(&OTHER_STARTED).await;
+            RUNNING_IN_GROUP.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        #[rye(serial(group = "db"))]
+        async fn test1(_: &mut crate::Context<'_>) {
+            run_in_group().await;
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        #[rye(serial(group = "db"))]
+        async fn test2(_: &mut crate::Context<'_>) {
+            run_in_group().await;
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        async fn test3(_: &mut crate::Context<'_>) {
+            OTHER_STARTED.store(true, Ordering::SeqCst);
+            wait_for(&GROUP_STARTED).await;
+        }
+
+        let tests = vec![test1, test2, test3];
+        let config = SessionConfig::default();
+        let mut rt = default_runtime();
+        let mut spawner = rt.spawner();
+        let report = rt
+            .block_on(async {
+                let mut sess = Session::new(&tests, &mut spawner, &config);
+                sess.run().await
+            })
+            .unwrap();
+        assert!(report.is_passed());
+        assert_eq!(report.passed().len(), 3);
+    }
+
+    #[test]
+    fn config_from_args() {
+        let config = SessionConfig::from_args(vec![
+            "rye",
+            "foo",
+            "bar",
+            "--skip",
+            "baz",
+            "--exact",
+            "--retries",
+            "3",
+            "--repeat=2",
+        ])
+        .unwrap();
+        assert_eq!(config.filter_patterns, vec!["foo", "bar"]);
+        assert_eq!(config.skip_patterns, vec!["baz"]);
+        assert_eq!(config.match_mode, MatchMode::Exact);
+        assert_eq!(config.retries, 3);
+        assert_eq!(config.repeat, Some(2));
+
+        assert!(SessionConfig::from_args(vec!["rye", "--exact", "--regex"]).is_err());
+        assert!(SessionConfig::from_args(vec!["rye", "--repeat", "0"]).is_err());
+    }
+
+    #[test]
+    fn config_typed_options() {
+        let mut config = SessionConfig::default();
+        config
+            .filter("foo")
+            .skip("bar")
+            .match_mode(MatchMode::Exact)
+            .retries(2)
+            .seed(42);
+        assert_eq!(config.filter_patterns, vec!["foo"]);
+        assert_eq!(config.skip_patterns, vec!["bar"]);
+        assert_eq!(config.match_mode, MatchMode::Exact);
+        assert_eq!(config.retries, 2);
+        assert_eq!(config.seed, Some(42));
+
+        config
+            .match_mode(MatchMode::Regex)
+            .list(true)
+            .ignored(true)
+            .format(OutputFormat::Json)
+            .color(ColorConfig::Never)
+            .keep_temp(true)
+            .update_snapshots(true)
+            .isolate(true)
+            .repeat(3)
+            .until_failure(true)
+            .failed(true)
+            .failed_first(true)
+            .partition(Partition::count(2, 3));
+        assert_eq!(config.match_mode, MatchMode::Regex);
+        assert!(config.list_tests);
+        assert!(config.ignored);
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.color, ColorConfig::Never);
+        assert!(config.keep_temp);
+        assert!(config.update_snapshots);
+        assert!(config.isolate);
+        assert_eq!(config.repeat, Some(3));
+        assert!(config.until_failure);
+        assert!(config.failed);
+        assert!(config.failed_first);
+        assert_eq!(config.partition, Some(Partition::count(2, 3)));
+    }
+
+    #[test]
+    fn config_typed_shuffle_options() {
+        let mut config = SessionConfig::default();
+        config.shuffle(true);
+        assert!(config.shuffle);
+        assert_eq!(config.shuffle_seed, None);
+        assert!(!config.shuffle_sections);
+
+        let mut config = SessionConfig::default();
+        config.shuffle_seed(7);
+        assert!(config.shuffle);
+        assert_eq!(config.shuffle_seed, Some(7));
+
+        let mut config = SessionConfig::default();
+        config.shuffle_sections(true);
+        assert!(config.shuffle);
+        assert!(config.shuffle_sections);
+    }
+
+    #[test]
+    fn parse_partition() {
+        assert_eq!(
+            "hash:1/3".parse::<Partition>().unwrap(),
+            Partition::hash(1, 3)
+        );
+        assert_eq!(
+            "count:3/3".parse::<Partition>().unwrap(),
+            Partition::count(3, 3)
+        );
+        assert!("hash:0/3".parse::<Partition>().is_err());
+        assert!("count:4/3".parse::<Partition>().is_err());
+        assert!("count:1/0".parse::<Partition>().is_err());
+        assert!("range:1/3".parse::<Partition>().is_err());
+        assert!("hash".parse::<Partition>().is_err());
+        assert!("hash:1".parse::<Partition>().is_err());
+        assert!("hash:a/3".parse::<Partition>().is_err());
+    }
+
+    #[test]
+    fn disjoint_partitions() {
+        let names: Vec<String> = (0..100).map(|i| format!("tests::case{}", i)).collect();
+        for &kind in &["hash", "count"] {
+            let partitions: Vec<Partition> = (1..=4)
+                .map(|k| format!("{}:{}/4", kind, k).parse().unwrap())
+                .collect();
+            for (i, name) in names.iter().enumerate() {
+                // each test case belongs to exactly one of the partitions.
+                let count = partitions
+                    .iter()
+                    .filter(|partition| partition.contains(i, name))
+                    .count();
+                assert_eq!(
+                    count, 1,
+                    "{} belongs to {} partitions ({})",
+                    name, count, kind
+                );
+            }
         }
     }
 }
@@ -0,0 +1,124 @@
+//! Conditions to skip the test cases, declared on the test attribute.
+
+#![allow(missing_docs)]
+
+use std::{env, fs, path::Path};
+
+/// A condition evaluated before running the test case.
+#[derive(Debug)]
+pub enum SkipCondition {
+    /// `#[rye(skip_if = path::to::fn)]`: skip if the function returns `true`.
+    If { name: &'static str, f: fn() -> bool },
+    /// `#[rye(require_env = "VAR")]`: skip unless the environment variable is set.
+    Env(&'static str),
+    /// `#[rye(require_cmd = "cmd")]`: skip unless the command is found.
+    Cmd(&'static str),
+}
+
+impl SkipCondition {
+    /// Evaluate the condition and return the reason to skip the test case, if any.
+    pub(crate) fn check(&self) -> Option<String> {
+        match *self {
+            SkipCondition::If { name, f } if f() => Some(format!("{} returned true", name)),
+            SkipCondition::Env(var) if env::var_os(var).is_none() => {
+                Some(format!("environment variable `{}` is not set", var))
+            }
+            SkipCondition::Cmd(cmd) if !command_exists(cmd) => {
+                Some(format!("command `{}` is not found", cmd))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the kind and the value of condition, used in the test list.
+    pub(crate) fn describe(&self) -> (&'static str, &'static str) {
+        match *self {
+            SkipCondition::If { name, .. } => ("skip_if", name),
+            SkipCondition::Env(var) => ("require_env", var),
+            SkipCondition::Cmd(cmd) => ("require_cmd", cmd),
+        }
+    }
+}
+
+/// Return whether the command can be executed, by searching it in `PATH`
+/// unless it contains a path separator.
+fn command_exists(cmd: &str) -> bool {
+    let is_executable = |path: &Path| {
+        if cfg!(windows) && path.extension().is_none() {
+            path.with_extension("exe").is_file()
+        } else {
+            path.metadata().map_or(false, |metadata| {
+                metadata.is_file() && has_execute_bits(&metadata)
+            })
+        }
+    };
+
+    let path = Path::new(cmd);
+    if path.components().count() > 1 {
+        return is_executable(path);
+    }
+    env::var_os("PATH").map_or(false, |paths| {
+        env::split_paths(&paths).any(|dir| is_executable(&dir.join(cmd)))
+    })
+}
+
+#[cfg(unix)]
+fn has_execute_bits(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt as _;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn has_execute_bits(_: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_conditions() {
+        fn yes() -> bool {
+            true
+        }
+        fn no() -> bool {
+            false
+        }
+
+        let skip_if = |name, f: fn() -> bool| SkipCondition::If { name, f };
+        assert_eq!(
+            skip_if("yes", yes).check(),
+            Some("yes returned true".to_owned())
+        );
+        assert_eq!(skip_if("no", no).check(), None);
+
+        assert_eq!(SkipCondition::Env("PATH").check(), None);
+        assert!(SkipCondition::Env("RYE_UNDEFINED_VARIABLE_FOR_TEST")
+            .check()
+            .is_some());
+
+        assert_eq!(SkipCondition::Cmd("cargo").check(), None);
+        assert!(SkipCondition::Cmd("rye-undefined-command-for-test")
+            .check()
+            .is_some());
+    }
+    #[cfg(unix)]
+    #[test]
+    fn non_executable_command() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let path = env::temp_dir().join(format!("rye-skip-test-{}", std::process::id()));
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        let cmd = path.to_str().unwrap();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let not_executable = command_exists(cmd);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        let executable = command_exists(cmd);
+        fs::remove_file(&path).unwrap();
+
+        assert!(!not_executable);
+        assert!(executable);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{diff::Diff, random, temp::sanitize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The name of environment variable to accept the new snapshots.
+const UPDATE_SNAPSHOTS_ENV: &str = "RYE_UPDATE_SNAPSHOTS";
+
+/// Return whether the mismatched snapshots should be overwritten
+/// according to the environment variable.
+pub(crate) fn update_snapshots_from_env() -> bool {
+    env::var_os(UPDATE_SNAPSHOTS_ENV).map_or(false, |value| value == "1")
+}
+
+/// The location of a snapshot file.
+#[derive(Debug)]
+pub(crate) struct Snapshot {
+    path: PathBuf,
+}
+
+impl Snapshot {
+    /// Determine the path of snapshot file stored next to the test source.
+    ///
+    /// The snapshot files are placed in the `snapshots` directory next to the
+    /// source file, and are named after the test name and the path of sections.
+    /// If any of the names cannot be written as is, a hash of the original
+    /// names is appended so that the sanitized file names never collide.
+    pub(crate) fn new(
+        manifest_dir: &str,
+        source_file: &str,
+        test_name: &str,
+        section_path: &[&str],
+        index: usize,
+    ) -> Self {
+        let source_file = resolve_source_file(Path::new(manifest_dir), Path::new(source_file));
+        let dir = source_file
+            .parent()
+            .map_or_else(|| PathBuf::from("snapshots"), |dir| dir.join("snapshots"));
+
+        let stem = source_file
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let components: Vec<&str> = Some(&*stem)
+            .into_iter()
+            .chain(test_name.split("::"))
+            .chain(section_path.iter().copied())
+            .collect();
+
+        let mut name = components
+            .iter()
+            .map(|component| sanitize(component))
+            .collect::<Vec<_>>()
+            .join("__");
+        if !components.iter().all(|component| is_verbatim(component)) {
+            let hash = random::hash(0, components.join("\0").as_bytes());
+            name += &format!("-{:08x}", hash as u32);
+        }
+        if index > 0 {
+            name += &format!("-{}", index);
+        }
+        name += ".snap";
+
+        Self {
+            path: dir.join(name),
+        }
+    }
+
+    fn new_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".new");
+        path.into()
+    }
+
+    /// Compare the content with the stored snapshot.
+    ///
+    /// When the snapshot is missing or does not match, the content is written
+    /// to the `.snap.new` file for review, or the snapshot is overwritten in
+    /// the update mode.
+    pub(crate) fn assert(&self, actual: &str, update: bool) -> Result<(), (String, Option<Diff>)> {
+        let expected = match fs::read_to_string(&self.path) {
+            Ok(expected) => Some(expected),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                return Err((
+                    format!(
+                        "failed to read the snapshot {}: {}",
+                        self.path.display(),
+                        err
+                    ),
+                    None,
+                ))
+            }
+        };
+
+        if expected
+            .as_ref()
+            .map_or(false, |expected| expected == actual)
+        {
+            let _ = fs::remove_file(self.new_path());
+            return Ok(());
+        }
+
+        if update {
+            return write_file(&self.path, actual)
+                .map(|()| {
+                    let _ = fs::remove_file(self.new_path());
+                })
+                .map_err(|reason| (reason, None));
+        }
+
+        let new_path = self.new_path();
+        write_file(&new_path, actual).map_err(|reason| (reason, None))?;
+
+        match expected {
+            Some(expected) => Err((
+                format!(
+                    "snapshot mismatch: {}\nthe new snapshot is written to {}; \
+                     review it and rename it or rerun with --update-snapshots to accept",
+                    self.path.display(),
+                    new_path.display(),
+                ),
+                Some(Diff::new("expected", "actual", &expected, actual)),
+            )),
+            None => Err((
+                format!(
+                    "snapshot not found: {}\nthe new snapshot is written to {}; \
+                     review it and rename it or rerun with --update-snapshots to accept",
+                    self.path.display(),
+                    new_path.display(),
+                ),
+                None,
+            )),
+        }
+    }
+}
+
+/// Serialize a value into the pretty-printed JSON for snapshots.
+#[cfg(feature = "serde")]
+pub fn to_json<T: ?Sized>(value: &T) -> String
+where
+    T: serde_crate::Serialize,
+{
+    serde_json::to_string_pretty(value)
+        .unwrap_or_else(|err| format!("<serialization error: {}>", err))
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("failed to create {}: {}", dir.display(), err))?;
+    }
+    fs::write(path, content).map_err(|err| format!("failed to write {}: {}", path.display(), err))
+}
+
+/// Return whether the name is written in the snapshot file name as is.
+///
+/// The names joined with `__` are distinguished from each other as long as
+/// they are non-empty and neither contain `__` nor start or end with `_`.
+/// `-` is also excluded since it precedes the index and the hash.
+fn is_verbatim(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('_')
+        && !name.ends_with('_')
+        && !name.contains("__")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Resolve the path of the source file returned by `file!()`.
+///
+/// The path is relative to the root of workspace rather than the package,
+/// so the ancestors of the manifest directory are also searched.
+fn resolve_source_file(manifest_dir: &Path, source_file: &Path) -> PathBuf {
+    if source_file.is_absolute() {
+        return source_file.into();
+    }
+    manifest_dir
+        .ancestors()
+        .map(|dir| dir.join(source_file))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| manifest_dir.join(source_file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_path() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let snapshot = Snapshot::new(
+            manifest_dir,
+            file!(),
+            "foo::bar",
+            &["with section", "nested"],
+            0,
+        );
+        assert_eq!(
+            snapshot.path,
+            Path::new(manifest_dir)
+                .join("src/snapshots/snapshot__foo__bar__with_section__nested-fd7c7226.snap")
+        );
+
+        let snapshot = Snapshot::new(manifest_dir, file!(), "foo::bar", &["nested"], 0);
+        assert_eq!(
+            snapshot.path,
+            Path::new(manifest_dir).join("src/snapshots/snapshot__foo__bar__nested.snap")
+        );
+
+        let snapshot = Snapshot::new(manifest_dir, file!(), "foo", &[], 2);
+        assert_eq!(
+            snapshot.path,
+            Path::new(manifest_dir).join("src/snapshots/snapshot__foo-2.snap")
+        );
+    }
+
+    #[test]
+    fn snapshot_path_collision() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let path = |section_path: &[&str], index| {
+            Snapshot::new(manifest_dir, file!(), "foo", section_path, index).path
+        };
+        let paths = vec![
+            path(&["a b"], 0),
+            path(&["a_b"], 0),
+            path(&["a/b"], 0),
+            path(&["a", "b"], 0),
+            path(&["a__b"], 0),
+            path(&["a_", "b"], 0),
+            path(&["a", "_b"], 0),
+            path(&["a-2"], 0),
+            path(&["a"], 2),
+        ];
+        for (i, p1) in paths.iter().enumerate() {
+            for p2 in &paths[i + 1..] {
+                assert_ne!(p1, p2);
+            }
+        }
+    }
+}
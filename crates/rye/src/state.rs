@@ -0,0 +1,140 @@
+//! Persistence of the test cases failed in the last runs.
+//!
+//! The names of failed test cases are stored in `<target dir>/rye/<binary>.failed`,
+//! one test case per line along with the path of sections where it failed,
+//! so that they can be re-executed with `--failed` or `--failed-first`.
+
+use crate::{
+    filter::SECTION_SEPARATOR,
+    isolate::{escape, unescape},
+};
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf};
+
+/// The set of test cases failed in the last runs.
+#[derive(Debug, Default)]
+pub(crate) struct FailedTests {
+    path: Option<PathBuf>,
+    tests: BTreeMap<String, Vec<String>>,
+}
+
+impl FailedTests {
+    /// Load the failed test cases from the state file of the current test binary.
+    pub(crate) fn load() -> Self {
+        let path = state_path();
+        let tests = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map_or_else(BTreeMap::new, |content| parse(&content));
+        Self { path, tests }
+    }
+
+    pub(crate) fn contains(&self, test_name: &str) -> bool {
+        self.tests.contains_key(test_name)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Return the paths of sections where the test cases failed, written in
+    /// the same form as the filters.
+    pub(crate) fn paths(&self) -> impl Iterator<Item = String> + '_ {
+        self.tests.iter().map(|(name, section_path)| {
+            let mut path = name.clone();
+            for section in section_path {
+                path += SECTION_SEPARATOR;
+                path += section;
+            }
+            path
+        })
+    }
+
+    /// Record the result of a test case run in the current session.
+    ///
+    /// The test cases not run in the current session are kept as they are.
+    pub(crate) fn update(&mut self, test_name: &str, failed_section: Option<&[String]>) {
+        match failed_section {
+            Some(section_path) => {
+                self.tests
+                    .insert(test_name.to_owned(), section_path.to_owned());
+            }
+            None => {
+                self.tests.remove(test_name);
+            }
+        }
+    }
+
+    /// Write the failed test cases to the state file.
+    pub(crate) fn save(&self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        if self.tests.is_empty() {
+            return match fs::remove_file(path) {
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            };
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = String::new();
+        for (name, section_path) in &self.tests {
+            content += &escape(name);
+            for section in section_path {
+                content += "\t";
+                content += &escape(section);
+            }
+            content += "\n";
+        }
+        fs::write(path, content)
+    }
+}
+
+fn parse(content: &str) -> BTreeMap<String, Vec<String>> {
+    content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t').map(unescape);
+            let name = fields.next().unwrap_or_default();
+            (name, fields.collect())
+        })
+        .collect()
+}
+
+/// Determine the path of state file from the location of the current test binary.
+fn state_path() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let stem = exe.file_stem()?.to_str()?;
+
+    // Strip the hash appended by Cargo (e.g. `test_harness-0123456789abcdef`).
+    let name = match stem.rfind('-') {
+        Some(i) if stem[i + 1..].chars().all(|c| c.is_ascii_hexdigit()) => &stem[..i],
+        _ => stem,
+    };
+
+    // The test binaries are placed in `<target dir>/<profile>/deps`.
+    let mut dir = exe.parent()?;
+    if dir.file_name().map_or(false, |name| name == "deps") {
+        dir = dir.parent()?;
+    }
+
+    Some(dir.join("rye").join(format!("{}.failed", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_state() {
+        let tests = parse("foo\nbar::baz\tsection\\tname\tnested\n\n");
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests["foo"], Vec::<String>::new());
+        assert_eq!(tests["bar::baz"], vec!["section\tname", "nested"]);
+    }
+}
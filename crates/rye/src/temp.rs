@@ -0,0 +1,87 @@
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Create a new, uniquely named temporary directory for the specified test case.
+pub(crate) fn create_temp_dir(test_name: &str) -> io::Result<PathBuf> {
+    let test_name = sanitize(test_name);
+    loop {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("rye-{}-{}-{}", process::id(), id, test_name));
+        match fs::create_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Replace the characters other than ASCII alphanumerics, `_` and `-` with `_`
+/// so that the string can be used as a part of file names.
+pub(crate) fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, thread};
+
+    #[test]
+    fn sanitized_names() {
+        assert_eq!(sanitize("tests::foo_bar-1"), "tests__foo_bar-1");
+        assert_eq!(sanitize("with space/and\\slash"), "with_space_and_slash");
+        assert_eq!(sanitize("ü."), "__");
+    }
+
+    #[test]
+    fn unique_temp_dirs() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    (0..8)
+                        .map(|_| create_temp_dir("tests::same name").unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let paths: Vec<PathBuf> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        let unique: HashSet<_> = paths.iter().collect();
+        assert_eq!(unique.len(), paths.len());
+        for path in &paths {
+            assert!(path.is_dir());
+            let name = path.file_name().unwrap().to_str().unwrap();
+            assert!(name.ends_with("-tests__same_name"), "{}", name);
+            fs::remove_dir_all(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn existing_temp_dir_is_skipped() {
+        // the directory left by another process with the same ID.
+        let id = NEXT_ID.load(Ordering::SeqCst);
+        let existing = env::temp_dir().join(format!("rye-{}-{}-existing", process::id(), id));
+        fs::create_dir_all(&existing).unwrap();
+
+        let path = create_temp_dir("existing").unwrap();
+        assert_ne!(path, existing);
+        assert!(path.is_dir());
+
+        fs::remove_dir_all(&path).unwrap();
+        fs::remove_dir_all(&existing).unwrap();
+    }
+}
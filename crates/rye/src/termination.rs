@@ -1,45 +1,151 @@
-/// Arbitrary return values in test cases.
-pub trait Termination: sealed::Sealed {
-    #[doc(hidden)] // TODO: dox
-    fn exit() -> Self;
+//! Conversion from the return values of test cases into their results.
+
+use std::{any::Any, error::Error as StdError, fmt};
 
-    #[doc(hidden)] // TODO: dox
-    fn into_result(self) -> anyhow::Result<()>;
+/// The result of a test case, determined from its return value.
+#[derive(Debug)]
+pub enum TestResult {
+    /// The test case passed.
+    Passed,
+    /// The test case failed with the specified error.
+    Failed(anyhow::Error),
+    /// The test case was skipped for the specified reason.
+    Skipped(String),
+}
+
+/// Arbitrary return values in test cases.
+///
+/// The return value of a test case is converted into [`TestResult`] in order to
+/// determine whether the test case passed, failed or was skipped. This trait
+/// is implemented for the following types:
+///
+/// * `()` and [`TestResult`] itself.
+/// * `Result<T, E>`, where `T: Termination` and `E: Debug`. The test case
+///   fails with the debug representation of `E` when it returns `Err`, except
+///   that `anyhow::Error` and `Box<dyn Error + Send + Sync>` are kept as they
+///   are along with their sources.
+/// * `i32`, interpreted as a process exit code: `0` is a pass, `77` (the
+///   conventional exit code for skipped tests) is a skip and the others are
+///   failures.
+///
+/// It can be implemented for user-defined types in order to return the custom
+/// outcomes from test cases:
+///
+/// ```
+/// use rye::{Termination, TestResult};
+///
+/// enum Check {
+///     Ok,
+///     Unsupported(&'static str),
+///     Mismatch { expected: u32, actual: u32 },
+/// }
+///
+/// impl Termination for Check {
+///     fn into_result(self) -> TestResult {
+///         match self {
+///             Check::Ok => TestResult::Passed,
+///             Check::Unsupported(reason) => TestResult::Skipped(reason.into()),
+///             Check::Mismatch { expected, actual } => TestResult::Failed(anyhow::anyhow!(
+///                 "expected {}, but got {}",
+///                 expected,
+///                 actual
+///             )),
+///         }
+///     }
+/// }
+/// ```
+pub trait Termination {
+    /// Convert the return value into the result of test case.
+    fn into_result(self) -> TestResult;
 }
 
 impl Termination for () {
-    #[doc(hidden)] // TODO: dox
     #[inline]
-    fn exit() {}
+    fn into_result(self) -> TestResult {
+        TestResult::Passed
+    }
+}
 
-    #[doc(hidden)] // TODO: dox
+impl Termination for TestResult {
     #[inline]
-    fn into_result(self) -> anyhow::Result<()> {
-        Ok(())
+    fn into_result(self) -> TestResult {
+        self
     }
 }
 
-impl<E> Termination for Result<(), E>
+impl<T, E> Termination for Result<T, E>
 where
-    E: Into<anyhow::Error>,
+    T: Termination,
+    E: fmt::Debug + 'static,
 {
-    #[doc(hidden)] // TODO: dox
     #[inline]
-    fn exit() -> Self {
-        Ok(())
+    fn into_result(self) -> TestResult {
+        match self {
+            Ok(value) => value.into_result(),
+            Err(err) => TestResult::Failed(into_error(err)),
+        }
     }
+}
 
-    #[doc(hidden)] // TODO: dox
+/// Convert the error value into `anyhow::Error`, without formatting it if it
+/// is already an error object.
+fn into_error<E>(err: E) -> anyhow::Error
+where
+    E: fmt::Debug + 'static,
+{
+    let mut err = Some(err);
+    let any = &mut err as &mut dyn Any;
+    if let Some(err) = any.downcast_mut::<Option<anyhow::Error>>() {
+        return err.take().unwrap();
+    }
+    if let Some(err) = any.downcast_mut::<Option<Box<dyn StdError + Send + Sync>>>() {
+        return anyhow::anyhow!(err.take().unwrap());
+    }
+    anyhow::anyhow!("{:?}", err.unwrap())
+}
+
+/// The exit code that indicates the test case is skipped.
+const EXIT_CODE_SKIPPED: i32 = 77;
+
+impl Termination for i32 {
     #[inline]
-    fn into_result(self) -> anyhow::Result<()> {
-        self.map_err(Into::into)
+    fn into_result(self) -> TestResult {
+        match self {
+            0 => TestResult::Passed,
+            EXIT_CODE_SKIPPED => TestResult::Skipped(format!("exited with {}", self)),
+            code => TestResult::Failed(anyhow::anyhow!("exited with {}", code)),
+        }
     }
 }
 
-mod sealed {
-    pub trait Sealed {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
 
-    impl Sealed for () {}
+    fn failure<E>(err: E) -> anyhow::Error
+    where
+        E: fmt::Debug + 'static,
+    {
+        match Err::<(), E>(err).into_result() {
+            TestResult::Failed(err) => err,
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn error_values() {
+        let err = failure(anyhow::anyhow!("root cause").context("failed to run"));
+        assert_eq!(err.to_string(), "failed to run");
+        assert_eq!(err.chain().count(), 2);
+        assert_eq!(err.root_cause().to_string(), "root cause");
 
-    impl<E> Sealed for Result<(), E> where E: Into<anyhow::Error> {}
+        let boxed: Box<dyn StdError + Send + Sync> =
+            Box::new(io::Error::new(io::ErrorKind::Other, "boxed"));
+        let err = failure(boxed);
+        assert_eq!(err.to_string(), "boxed");
+
+        let err = failure("not an error");
+        assert_eq!(err.to_string(), "\"not an error\"");
+    }
 }
@@ -1,8 +1,20 @@
 #![allow(missing_docs)]
 
 use crate::{
+    diff::Diff,
+    dynamic::DynamicSections,
+    filter::{self, Filter},
+    fixture::{
+        Fixture, FixtureDesc, FixtureGuard, FixtureRequest, FixtureScope, FixtureSetup,
+        FixtureStore, FixtureValue,
+    },
+    global::{Acquired, GlobalKey, Globals},
+    isolate, random,
     report::{Outcome, Reporter, TestCaseSummary},
-    termination::Termination,
+    skip::SkipCondition,
+    snapshot::Snapshot,
+    temp,
+    termination::TestResult,
 };
 use futures_channel::oneshot;
 use futures_core::{
@@ -11,13 +23,25 @@ use futures_core::{
 };
 use maybe_unwind::{maybe_unwind, FutureMaybeUnwindExt as _, Unwind};
 use pin_project::pin_project;
+use rand::seq::SliceRandom as _;
 use rye_runtime::Spawner;
-use std::{fmt, marker::PhantomData, panic::AssertUnwindSafe, pin::Pin, ptr::NonNull};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Write as _},
+    fs, io,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    pin::Pin,
+    ptr::NonNull,
+    sync::Arc,
+};
 
 #[allow(missing_docs)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Location {
-    pub file: &'static str,
+    pub file: Cow<'static, str>,
     pub line: u32,
     pub column: u32,
 }
@@ -28,11 +52,28 @@ impl fmt::Display for Location {
     }
 }
 
+/// The lock held exclusively while running a test case.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// `#[rye(serial)]`: the anonymous lock shared by the test cases marked as `serial`.
+    Serial,
+    /// `#[rye(serial(group = "name"))]` or `#[rye(resource = "name")]`: the named lock.
+    Named(&'static str),
+}
+
 /// Metadata about a test case.
 #[derive(Debug)]
 pub struct TestDesc {
     pub name: TestName,
     pub location: Location,
+    pub globals: &'static [GlobalKey],
+    pub fixtures: &'static [FixtureRequest],
+    pub isolate: bool,
+    pub retries: Option<u32>,
+    pub resources: &'static [Resource],
+    pub skip_conditions: &'static [SkipCondition],
+    pub xfail: Option<&'static str>,
+    pub sections: &'static [Section],
 }
 
 impl TestDesc {
@@ -62,9 +103,9 @@ pub(crate) type SectionId = u64;
 
 #[derive(Debug)]
 pub enum TestFn {
-    Async(fn(ContextPtr) -> BoxFuture<'static, anyhow::Result<()>>),
-    AsyncLocal(fn(ContextPtr) -> LocalBoxFuture<'static, anyhow::Result<()>>),
-    Blocking(fn(ContextPtr) -> anyhow::Result<()>),
+    Async(fn(ContextPtr) -> BoxFuture<'static, TestResult>),
+    AsyncLocal(fn(ContextPtr) -> LocalBoxFuture<'static, TestResult>),
+    Blocking(fn(ContextPtr) -> TestResult),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -78,6 +119,25 @@ impl AsRef<str> for TestName {
     }
 }
 
+/// Options that control the execution of test cases.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct TestOptions {
+    /// Keep the temporary directories even if the test case is passed.
+    pub(crate) keep_temp: bool,
+    /// Overwrite the mismatched snapshots instead of failing.
+    pub(crate) update_snapshots: bool,
+    /// Run all test cases in separate processes.
+    pub(crate) isolate: bool,
+    /// Whether the current process is a child process spawned for isolation.
+    pub(crate) is_child: bool,
+    /// The number of retries for failing test cases.
+    pub(crate) retries: u32,
+    /// The seed used to shuffle the order of test plans, if enabled.
+    pub(crate) shuffle_seed: Option<u64>,
+    /// The seed of random number generators provided to test cases.
+    pub(crate) seed: u64,
+}
+
 pub struct TestCase {
     pub desc: TestDesc,
     pub testfn: TestFn,
@@ -85,46 +145,161 @@ pub struct TestCase {
 }
 
 impl TestCase {
-    pub(crate) fn spawn<R>(
+    /// Prepare to spawn the test case, acquiring the global values it requests.
+    ///
+    /// The global values are not acquired if the test case is skipped or run
+    /// in a separate process.
+    pub(crate) async fn prepare(
         &'static self,
-        spawner: &mut dyn Spawner,
-        reporter: R,
-    ) -> anyhow::Result<Handle>
+        globals: &Globals,
+        filter: &Filter,
+        options: TestOptions,
+    ) -> Prepared {
+        let plans = filter.select_plans(self);
+
+        let skipped = self
+            .desc
+            .skip_conditions
+            .iter()
+            .find_map(SkipCondition::check);
+        let state = if let Some(reason) = skipped {
+            PreparedState::Skipped(reason)
+        } else if (options.isolate || self.desc.isolate) && !options.is_child {
+            PreparedState::Isolated
+        } else {
+            PreparedState::Ready(globals.acquire(&self.desc).await)
+        };
+
+        Prepared {
+            test: self,
+            plans,
+            options,
+            state,
+        }
+    }
+}
+
+/// A test case ready to be spawned.
+pub(crate) struct Prepared {
+    test: &'static TestCase,
+    plans: Vec<&'static TestPlan>,
+    options: TestOptions,
+    state: PreparedState,
+}
+
+enum PreparedState {
+    Skipped(String),
+    Isolated,
+    Ready(anyhow::Result<Acquired>),
+}
+
+impl Prepared {
+    pub(crate) fn spawn<R>(self, spawner: &mut dyn Spawner, reporter: R) -> anyhow::Result<Handle>
     where
         R: Reporter + Send + 'static,
     {
-        let mut inner = TestInner {
-            desc: &self.desc,
-            plans: self.plans,
-        };
+        let Prepared {
+            test,
+            plans,
+            options,
+            state,
+        } = self;
         let mut reporter = reporter;
 
         let (tx, rx) = oneshot::channel();
-        match self.testfn {
+        let handle = Handle {
+            rx,
+            desc: &test.desc,
+        };
+
+        let globals = match state {
+            PreparedState::Skipped(reason) => {
+                reporter.test_case_starting(&test.desc);
+                let summary = TestCaseSummary {
+                    desc: &test.desc,
+                    outcome: Outcome::Skipped {
+                        location: test.desc.location.clone(),
+                        reason,
+                    },
+                    failed_attempts: vec![],
+                    section_path: vec![],
+                };
+                reporter.test_case_ended(&summary);
+                let _ = tx.send(summary);
+                return Ok(handle);
+            }
+
+            PreparedState::Isolated => {
+                // Pass the paths of selected sections to the child process,
+                // unless the whole test case is selected.
+                let desc = &test.desc;
+                let filters = if plans.len() == test.plans.len() {
+                    vec![desc.name().to_owned()]
+                } else {
+                    plans
+                        .iter()
+                        .map(|plan| filter::plan_path(desc, plan))
+                        .collect()
+                };
+                spawner.spawn_blocking(Box::new(move || {
+                    reporter.test_case_starting(desc);
+                    let summary = isolate::run_in_subprocess(desc, &filters, options);
+                    reporter.test_case_ended(&summary);
+                    let _ = tx.send(summary);
+                }))?;
+                return Ok(handle);
+            }
+
+            PreparedState::Ready(Ok(globals)) => globals,
+            PreparedState::Ready(Err(err)) => {
+                reporter.test_case_starting(&test.desc);
+                let summary = TestCaseSummary {
+                    desc: &test.desc,
+                    outcome: Outcome::Errored(err),
+                    failed_attempts: vec![],
+                    section_path: vec![],
+                };
+                reporter.test_case_ended(&summary);
+                let _ = tx.send(summary);
+                return Ok(handle);
+            }
+        };
+
+        let mut inner = TestInner {
+            desc: &test.desc,
+            plans,
+            globals,
+            fixtures: FixtureStore::default(),
+            options,
+        };
+
+        // The acquired global values must be released before notifying the completion
+        // so that they can be torn down at the end of the session.
+        match test.testfn {
             TestFn::Async(f) => {
                 spawner.spawn(Box::pin(async move {
                     let summary = inner.run_async(&mut reporter, f).await;
+                    drop(inner);
                     let _ = tx.send(summary);
                 }))?;
             }
             TestFn::AsyncLocal(f) => {
                 spawner.spawn_local(Box::pin(async move {
                     let summary = inner.run_async(&mut reporter, f).await;
+                    drop(inner);
                     let _ = tx.send(summary);
                 }))?;
             }
             TestFn::Blocking(f) => {
                 spawner.spawn_blocking(Box::new(move || {
                     let summary = inner.run_blocking(&mut reporter, f);
+                    drop(inner);
                     let _ = tx.send(summary);
                 }))?;
             }
         }
 
-        Ok(Handle {
-            rx,
-            desc: &self.desc,
-        })
+        Ok(handle)
     }
 }
 
@@ -149,66 +324,376 @@ impl Future for Handle {
 
 struct TestInner {
     desc: &'static TestDesc,
-    plans: &'static [TestPlan],
+    plans: Vec<&'static TestPlan>,
+    globals: Acquired,
+    fixtures: FixtureStore,
+    options: TestOptions,
 }
 
 impl TestInner {
+    /// Return the maximum number of attempts to run each test plan.
+    ///
+    /// The test cases expected to fail are not retried. The test-scoped fixtures
+    /// are set up again before each retry, so that the failed attempt does not
+    /// affect the subsequent ones.
+    fn max_attempts(&self) -> u32 {
+        if self.desc.xfail.is_some() {
+            return 1;
+        }
+        1 + self.desc.retries.unwrap_or(self.options.retries)
+    }
+
+    /// Return the test plans in the order to be run.
+    fn plans(&self) -> Vec<&'static TestPlan> {
+        let mut plans = self.plans.clone();
+        if let Some(seed) = self.options.shuffle_seed {
+            plans.shuffle(&mut random::rng(seed, self.desc.name()));
+        }
+        plans
+    }
+
     async fn run_async<Fut>(
         &mut self,
         reporter: &mut (dyn Reporter + Send + 'static),
         f: fn(ContextPtr) -> Fut,
     ) -> TestCaseSummary
     where
-        Fut: Future<Output = anyhow::Result<()>>,
+        Fut: Future<Output = TestResult>,
     {
         reporter.test_case_starting(&self.desc);
 
         let mut outcome = Outcome::Passed;
-        for plan in self.plans {
-            let mut ctx = Context::new(reporter, plan);
-            let result = AssertUnwindSafe(f(unsafe { ctx.transmute() }))
-                .maybe_unwind()
-                .await;
-            if let Some(o) = ctx.check_outcome(result) {
+        let mut failed_attempts = vec![];
+        let mut section_path = vec![];
+        if let Some(o) = self.setup_fixtures(FixtureScope::Test).await {
+            outcome = o;
+        } else {
+            'plans: for plan in self.plans() {
+                let mut dynamic_sections = DynamicSections::default();
+                loop {
+                    for attempt in 1..=self.max_attempts() {
+                        match self
+                            .run_plan_async(reporter, plan, &mut dynamic_sections, f)
+                            .await
+                        {
+                            Some((o, _)) if o.is_failed() && attempt < self.max_attempts() => {
+                                failed_attempts.push(o);
+                                if let Some(o) = self.reset_fixtures(FixtureScope::Test).await {
+                                    outcome = o;
+                                    break 'plans;
+                                }
+                            }
+                            Some((o, path)) => {
+                                outcome = o;
+                                section_path = path;
+                                break 'plans;
+                            }
+                            None => break,
+                        }
+                    }
+                    // Run the test plan again until all dynamic sections are completed.
+                    match dynamic_sections.finish_run() {
+                        Ok(true) => (),
+                        Ok(false) => break,
+                        Err(err) => {
+                            outcome = Outcome::Errored(err);
+                            break 'plans;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(o) = self.teardown_fixtures(FixtureScope::Test).await {
+            if let Outcome::Passed = outcome {
                 outcome = o;
-                break;
             }
         }
 
         let summary = TestCaseSummary {
             desc: self.desc,
-            outcome,
+            outcome: outcome.expect(self.desc),
+            failed_attempts,
+            section_path,
         };
         reporter.test_case_ended(&summary);
 
         summary
     }
 
+    async fn run_plan_async<Fut>(
+        &self,
+        reporter: &mut (dyn Reporter + Send + 'static),
+        plan: &'static TestPlan,
+        dynamic_sections: &mut DynamicSections,
+        f: fn(ContextPtr) -> Fut,
+    ) -> Option<(Outcome, Vec<String>)>
+    where
+        Fut: Future<Output = TestResult>,
+    {
+        if let Some(o) = self.setup_fixtures(FixtureScope::Section).await {
+            return Some((o, vec![]));
+        }
+
+        let mut ctx = Context::new(
+            reporter,
+            self.desc,
+            plan,
+            &self.globals,
+            &self.fixtures,
+            dynamic_sections,
+            self.options,
+        );
+        let result = AssertUnwindSafe(f(unsafe { ctx.transmute() }))
+            .maybe_unwind()
+            .await;
+        let checked = ctx.check_outcome(result, &self.desc.location);
+        let temp_dir = ctx.temp_dir.take();
+        let section_path = ctx.section_path();
+
+        let teardown = self.teardown_fixtures(FixtureScope::Section).await;
+        let checked = checked.or(teardown);
+        self.cleanup_temp_dir(temp_dir, checked.as_ref());
+        checked.map(|o| (o, section_path))
+    }
+
     fn run_blocking(
         &mut self,
         reporter: &mut (dyn Reporter + Send),
-        f: fn(ContextPtr) -> anyhow::Result<()>,
+        f: fn(ContextPtr) -> TestResult,
     ) -> TestCaseSummary {
         reporter.test_case_starting(&self.desc);
 
         let mut outcome = Outcome::Passed;
-        for plan in self.plans {
-            let mut ctx = Context::new(reporter, plan);
-            let result = maybe_unwind(AssertUnwindSafe(|| f(unsafe { ctx.transmute() })));
-            if let Some(o) = ctx.check_outcome(result) {
+        let mut failed_attempts = vec![];
+        let mut section_path = vec![];
+        if let Some(o) = self
+            .check_blocking_fixtures()
+            .or_else(|| self.setup_fixtures_blocking(FixtureScope::Test))
+        {
+            outcome = o;
+        } else {
+            'plans: for plan in self.plans() {
+                let mut dynamic_sections = DynamicSections::default();
+                loop {
+                    for attempt in 1..=self.max_attempts() {
+                        match self.run_plan_blocking(reporter, plan, &mut dynamic_sections, f) {
+                            Some((o, _)) if o.is_failed() && attempt < self.max_attempts() => {
+                                failed_attempts.push(o);
+                                if let Some(o) = self.reset_fixtures_blocking(FixtureScope::Test) {
+                                    outcome = o;
+                                    break 'plans;
+                                }
+                            }
+                            Some((o, path)) => {
+                                outcome = o;
+                                section_path = path;
+                                break 'plans;
+                            }
+                            None => break,
+                        }
+                    }
+                    // Run the test plan again until all dynamic sections are completed.
+                    match dynamic_sections.finish_run() {
+                        Ok(true) => (),
+                        Ok(false) => break,
+                        Err(err) => {
+                            outcome = Outcome::Errored(err);
+                            break 'plans;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(o) = self.teardown_fixtures_blocking(FixtureScope::Test) {
+            if let Outcome::Passed = outcome {
                 outcome = o;
-                break;
             }
         }
 
         let summary = TestCaseSummary {
             desc: self.desc,
-            outcome,
+            outcome: outcome.expect(self.desc),
+            failed_attempts,
+            section_path,
         };
         reporter.test_case_ended(&summary);
 
         summary
     }
+
+    fn run_plan_blocking(
+        &self,
+        reporter: &mut (dyn Reporter + Send),
+        plan: &'static TestPlan,
+        dynamic_sections: &mut DynamicSections,
+        f: fn(ContextPtr) -> TestResult,
+    ) -> Option<(Outcome, Vec<String>)> {
+        if let Some(o) = self.setup_fixtures_blocking(FixtureScope::Section) {
+            return Some((o, vec![]));
+        }
+
+        let mut ctx = Context::new(
+            reporter,
+            self.desc,
+            plan,
+            &self.globals,
+            &self.fixtures,
+            dynamic_sections,
+            self.options,
+        );
+        let result = maybe_unwind(AssertUnwindSafe(|| f(unsafe { ctx.transmute() })));
+        let checked = ctx.check_outcome(result, &self.desc.location);
+        let temp_dir = ctx.temp_dir.take();
+        let section_path = ctx.section_path();
+
+        let teardown = self.teardown_fixtures_blocking(FixtureScope::Section);
+        let checked = checked.or(teardown);
+        self.cleanup_temp_dir(temp_dir, checked.as_ref());
+        checked.map(|o| (o, section_path))
+    }
+
+    /// Remove the temporary directory used in a test run, unless the run failed
+    /// or `--keep-temp` is specified.
+    fn cleanup_temp_dir(&self, temp_dir: Option<PathBuf>, outcome: Option<&Outcome>) {
+        let temp_dir = match temp_dir {
+            Some(temp_dir) => temp_dir,
+            None => return,
+        };
+
+        let failed = outcome.map_or(false, Outcome::is_failed);
+        if failed || self.options.keep_temp {
+            eprintln!(
+                "note: the temporary directory for `{}` is kept at {}",
+                self.desc.name(),
+                temp_dir.display()
+            );
+        } else if let Err(err) = fs::remove_dir_all(&temp_dir) {
+            eprintln!(
+                "warning: failed to remove the temporary directory {}: {}",
+                temp_dir.display(),
+                err
+            );
+        }
+    }
+
+    fn scoped_fixtures(
+        &self,
+        scope: FixtureScope,
+    ) -> impl DoubleEndedIterator<Item = &'static FixtureDesc> {
+        self.desc
+            .fixtures
+            .iter()
+            .map(|request| request.desc)
+            .filter(move |desc| desc.scope == scope)
+    }
+
+    fn store_fixture(&self, desc: &FixtureDesc, value: FixtureValue) -> Option<Outcome> {
+        match value {
+            FixtureValue::Owned(value) => {
+                self.fixtures.insert((desc.type_id)(), value);
+                None
+            }
+            FixtureValue::Shared(..) => Some(Outcome::Errored(anyhow::anyhow!(
+                "the fixture `{}` provided a shared value",
+                desc.name
+            ))),
+        }
+    }
+
+    async fn setup_fixtures(&self, scope: FixtureScope) -> Option<Outcome> {
+        for desc in self.scoped_fixtures(scope) {
+            let result = AssertUnwindSafe(async move {
+                match desc.setup {
+                    FixtureSetup::Blocking(setup) => setup(),
+                    FixtureSetup::Async(setup) => setup().await,
+                }
+            })
+            .maybe_unwind()
+            .await;
+            match result {
+                Ok(value) => self.store_fixture(desc, value)?,
+                Err(unwind) => return Some(Outcome::Panicked(format!("{:#}", unwind))),
+            };
+        }
+        None
+    }
+
+    async fn teardown_fixtures(&self, scope: FixtureScope) -> Option<Outcome> {
+        let mut outcome = None;
+        for desc in self.scoped_fixtures(scope).rev() {
+            if let Some(value) = self.fixtures.take((desc.type_id)()) {
+                let result = AssertUnwindSafe(async move {
+                    match desc.teardown {
+                        Some(teardown) => teardown(FixtureValue::Owned(value)).await,
+                        None => drop(value),
+                    }
+                })
+                .maybe_unwind()
+                .await;
+                if let Err(unwind) = result {
+                    outcome.get_or_insert(Outcome::Panicked(format!("{:#}", unwind)));
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Tear down the fixtures and set them up again for the next attempt.
+    async fn reset_fixtures(&self, scope: FixtureScope) -> Option<Outcome> {
+        match self.teardown_fixtures(scope).await {
+            Some(o) => Some(o),
+            None => self.setup_fixtures(scope).await,
+        }
+    }
+
+    /// Check if all fixtures used in the blocking test case can be set up without
+    /// the asynchronous context.
+    fn check_blocking_fixtures(&self) -> Option<Outcome> {
+        self.desc
+            .fixtures
+            .iter()
+            .find(|request| {
+                request.desc.scope != FixtureScope::Session && request.desc.requires_async()
+            })
+            .map(|request| {
+                Outcome::Errored(anyhow::anyhow!(
+                    "the fixture `{}` requires the asynchronous context and cannot be used \
+                     in blocking test cases unless it is session-scoped",
+                    request.desc.name
+                ))
+            })
+    }
+
+    fn setup_fixtures_blocking(&self, scope: FixtureScope) -> Option<Outcome> {
+        for desc in self.scoped_fixtures(scope) {
+            let setup = match desc.setup {
+                FixtureSetup::Blocking(setup) => setup,
+                FixtureSetup::Async(..) => unreachable!(),
+            };
+            match maybe_unwind(setup) {
+                Ok(value) => self.store_fixture(desc, value)?,
+                Err(unwind) => return Some(Outcome::Panicked(format!("{:#}", unwind))),
+            };
+        }
+        None
+    }
+
+    fn teardown_fixtures_blocking(&self, scope: FixtureScope) -> Option<Outcome> {
+        let mut outcome = None;
+        for desc in self.scoped_fixtures(scope).rev() {
+            if let Some(value) = self.fixtures.take((desc.type_id)()) {
+                if let Err(unwind) = maybe_unwind(AssertUnwindSafe(move || drop(value))) {
+                    outcome.get_or_insert(Outcome::Panicked(format!("{:#}", unwind)));
+                }
+            }
+        }
+        outcome
+    }
+
+    fn reset_fixtures_blocking(&self, scope: FixtureScope) -> Option<Outcome> {
+        self.teardown_fixtures_blocking(scope)
+            .or_else(|| self.setup_fixtures_blocking(scope))
+    }
 }
 
 #[repr(transparent)]
@@ -224,23 +709,55 @@ impl ContextPtr {
     }
 }
 
+/// The unwinding payload to terminate the test run by `skip!` or `fail!`.
+pub(crate) struct Abort;
+
 /// Context values while running the test case.
 pub struct Context<'a> {
+    desc: &'a TestDesc,
     plan: &'a TestPlan,
+    globals: &'a Acquired,
+    fixtures: &'a FixtureStore,
     #[allow(dead_code)]
     reporter: &'a mut (dyn Reporter + Send),
+    dynamic_sections: &'a mut DynamicSections,
     current_section: Option<&'static Section>,
+    entered_sections: Vec<SectionId>,
+    section_path: Vec<Cow<'static, str>>,
     outcome: Option<Outcome>,
+    temp_dir: Option<PathBuf>,
+    options: TestOptions,
+    num_snapshots: HashMap<Vec<Cow<'static, str>>, usize>,
+    rngs: HashMap<Vec<Cow<'static, str>>, random::Rng>,
     _marker: PhantomData<fn(&'a ()) -> &'a ()>,
 }
 
 impl<'a> Context<'a> {
-    pub(crate) fn new(reporter: &'a mut (dyn Reporter + Send), plan: &'a TestPlan) -> Self {
+    pub(crate) fn new(
+        reporter: &'a mut (dyn Reporter + Send),
+        desc: &'a TestDesc,
+        plan: &'a TestPlan,
+        globals: &'a Acquired,
+        fixtures: &'a FixtureStore,
+        dynamic_sections: &'a mut DynamicSections,
+        options: TestOptions,
+    ) -> Self {
+        dynamic_sections.start_run();
         Self {
+            desc,
             plan,
+            globals,
+            fixtures,
             reporter,
+            dynamic_sections,
             current_section: None,
+            entered_sections: vec![],
+            section_path: vec![],
             outcome: None,
+            temp_dir: None,
+            options,
+            num_snapshots: HashMap::new(),
+            rngs: HashMap::new(),
             _marker: PhantomData,
         }
     }
@@ -250,31 +767,165 @@ impl<'a> Context<'a> {
         ContextPtr(NonNull::from(&mut *self).cast::<Context<'static>>())
     }
 
+    /// Determine the outcome of test run from the returned result.
+    ///
+    /// The skips without the location are reported at the specified one.
     pub(crate) fn check_outcome(
         &mut self,
-        result: Result<anyhow::Result<()>, Unwind>,
+        result: Result<TestResult, Unwind>,
+        location: &'static Location,
     ) -> Option<Outcome> {
         match result {
-            Ok(Ok(())) => self.outcome.take(),
-            Ok(Err(err)) => Some(Outcome::Errored(err)),
-            Err(unwind) => Some(Outcome::Panicked(unwind)),
+            Ok(TestResult::Passed) => self.outcome.take(),
+            Ok(TestResult::Skipped(reason)) => self.outcome.take().or_else(|| {
+                Some(Outcome::Skipped {
+                    location: location.clone(),
+                    reason,
+                })
+            }),
+            Ok(TestResult::Failed(err)) => Some(self.merge_outcome(Outcome::Errored(err))),
+            // The unwinding started by `skip!` or `fail!`.
+            Err(unwind) if unwind.payload().is::<Abort>() => self.outcome.take(),
+            Err(unwind) => Some(self.merge_outcome(Outcome::Panicked(format!("{:#}", unwind)))),
         }
     }
 
-    #[inline]
-    fn exit<T>(&mut self) -> T
+    /// Merge the recorded outcome with the error or panic that ended the test run.
+    ///
+    /// As in `record_outcome`, the recorded failure takes precedence and the
+    /// error or panic is appended to it. The recorded skip is discarded.
+    fn merge_outcome(&mut self, outcome: Outcome) -> Outcome {
+        let also = match outcome {
+            Outcome::Errored(ref err) => format!("returned an error: {:#}", err),
+            Outcome::Panicked(ref msg) => format!("panicked: {}", msg),
+            _ => return self.outcome.take().unwrap_or(outcome),
+        };
+        match self.outcome.take() {
+            Some(Outcome::Failed {
+                location,
+                mut reason,
+                diff,
+            }) => {
+                if let Some(diff) = diff {
+                    let _ = write!(reason, "\n{}", diff);
+                }
+                let _ = write!(reason, "\nalso {}", also);
+                Outcome::Failed {
+                    location,
+                    reason,
+                    diff: None,
+                }
+            }
+            _ => outcome,
+        }
+    }
+
+    /// Return the path of sections the test run is currently in.
+    fn section_path(&self) -> Vec<String> {
+        self.section_path
+            .iter()
+            .map(|name| name.clone().into_owned())
+            .collect()
+    }
+
+    /// Retrieve the global value of the specified type.
+    ///
+    /// The value must be requested by the test case with `#[rye(global = Type)]`.
+    pub fn global<T>(&self) -> anyhow::Result<Arc<T>>
     where
-        T: Termination,
+        T: Send + Sync + 'static,
     {
-        T::exit()
+        self.globals.get::<T>()
+    }
+
+    /// Return the pseudo random number generator dedicated to the current section.
+    ///
+    /// The generator is seeded with the seed of the test run, the test name and
+    /// the path of the current section, so the generated values are reproduced
+    /// by specifying the seed printed on failure with `--seed` or `RYE_SEED`.
+    pub fn rng(&mut self) -> &mut random::Rng {
+        let seed = self.options.seed;
+        let desc = self.desc;
+        let section_path = &self.section_path;
+        self.rngs.entry(section_path.clone()).or_insert_with(|| {
+            let mut key = desc.name().to_owned();
+            for section in section_path {
+                key += "::";
+                key += &**section;
+            }
+            random::rng(seed, &key)
+        })
+    }
+
+    /// Return the path of the temporary directory dedicated to the current test run.
+    ///
+    /// The directory is created on the first call and is shared within the
+    /// execution of a section.  It is removed after the test run is completed
+    /// successfully, and kept otherwise (or when `--keep-temp` is specified)
+    /// with its path printed for debugging.
+    pub fn temp_dir(&mut self) -> io::Result<PathBuf> {
+        if let Some(ref temp_dir) = self.temp_dir {
+            return Ok(temp_dir.clone());
+        }
+        let temp_dir = temp::create_temp_dir(self.desc.name())?;
+        self.temp_dir.replace(temp_dir.clone());
+        Ok(temp_dir)
+    }
+
+    /// Record the outcome of the current test run.
+    ///
+    /// If an outcome has already been recorded (e.g. the abort was caught by
+    /// the test code), the failures take precedence over the skips and the
+    /// subsequent failures are appended to the first one.
+    fn record_outcome(&mut self, outcome: Outcome) {
+        match (&mut self.outcome, outcome) {
+            (
+                Some(Outcome::Failed { reason, diff, .. }),
+                Outcome::Failed {
+                    location,
+                    reason: new_reason,
+                    diff: new_diff,
+                },
+            ) => {
+                // The diff of the first failure is rendered in place, since only
+                // the last one is kept apart from the reason.
+                if let Some(diff) = diff.take() {
+                    let _ = write!(reason, "\n{}", diff);
+                }
+                let _ = write!(reason, "\nalso failed at {}: {}", location, new_reason);
+                *diff = new_diff;
+            }
+            (Some(Outcome::Failed { .. }), _)
+            | (Some(Outcome::Skipped { .. }), Outcome::Skipped { .. }) => (),
+            (recorded, outcome) => {
+                recorded.replace(outcome);
+            }
+        }
+    }
+
+    /// Terminate the execution of current test run by unwinding.
+    ///
+    /// The unwinding is caught by the test runner and the recorded outcome is
+    /// reported instead of a panic, so this can be called from the nested
+    /// helper functions as well as the test function itself.
+    fn abort(&mut self) -> ! {
+        debug_assert!(self.outcome.is_some());
+        panic::resume_unwind(Box::new(Abort))
     }
 }
 
 hidden_item! {
     impl Context<'_> {
         pub fn enter_section(&mut self, section: &'static Section) -> EnterSection {
-            let enabled = self.plan.is_enabled(section.id);
+            // The sections in closures could be reached many times,
+            // but each of them is executed at most once in a test run.
+            let enabled = self.plan.is_enabled(section.id)
+                && !self.entered_sections.contains(&section.id);
+            if enabled {
+                self.entered_sections.push(section.id);
+            }
             let last_section = self.current_section.replace(section);
+            self.section_path.push(Cow::Borrowed(section.name));
             EnterSection {
                 enabled,
                 last_section,
@@ -283,31 +934,103 @@ hidden_item! {
 
         pub fn leave_section(&mut self, enter: EnterSection) {
             self.current_section = enter.last_section;
+            self.section_path.pop();
+        }
+
+        pub fn enter_dynamic_section(&mut self, name: String) -> EnterDynamicSection {
+            let enabled = self.dynamic_sections.enter(&name);
+            if enabled {
+                self.section_path.push(Cow::Owned(name));
+            }
+            EnterDynamicSection { enabled }
+        }
+
+        pub fn leave_dynamic_section(&mut self, enter: EnterDynamicSection) {
+            if enter.enabled {
+                self.dynamic_sections.leave();
+                self.section_path.pop();
+            }
         }
 
+        pub fn check_snapshot(
+            &mut self,
+            manifest_dir: &str,
+            location: &'static Location,
+            content: String,
+        ) {
+            let index = self
+                .num_snapshots
+                .entry(self.section_path.clone())
+                .or_insert(0);
+            let section_path: Vec<&str> = self.section_path.iter().map(|name| &**name).collect();
+            let snapshot = Snapshot::new(
+                manifest_dir,
+                &location.file,
+                self.desc.name(),
+                &section_path,
+                *index,
+            );
+            *index += 1;
+            if let Err((reason, diff)) = snapshot.assert(&content, self.options.update_snapshots) {
+                self.record_outcome(Outcome::Failed {
+                    location: location.clone(),
+                    reason,
+                    diff,
+                });
+                self.abort()
+            }
+        }
 
-        pub fn skip<T>(&mut self, location: &'static Location, reason: fmt::Arguments<'_>) -> T
+        pub fn fixture<F>(&mut self) -> FixtureGuard<F>
         where
-            T: Termination,
+            F: Fixture,
         {
-            debug_assert!(self.outcome.is_none());
-            self.outcome.replace(Outcome::Skipped {
-                location,
+            let type_id = (F::DESC.type_id)();
+            match F::DESC.scope {
+                FixtureScope::Session => {
+                    let value = self
+                        .globals
+                        .get_any(type_id)
+                        .expect("the fixture is not acquired")
+                        .clone();
+                    FixtureGuard::shared(value, self.fixtures.clone())
+                }
+                FixtureScope::Test | FixtureScope::Section => {
+                    let value = self
+                        .fixtures
+                        .take(type_id)
+                        .expect("the fixture is not set up")
+                        .downcast::<F::Output>()
+                        .unwrap_or_else(|_| unreachable!("mismatched fixture type"));
+                    FixtureGuard::owned(*value, self.fixtures.clone())
+                }
+            }
+        }
+
+        pub fn skip(&mut self, location: &'static Location, reason: fmt::Arguments<'_>) -> ! {
+            self.record_outcome(Outcome::Skipped {
+                location: location.clone(),
                 reason: reason.to_string(),
             });
-            self.exit()
+            self.abort()
         }
 
-        pub fn fail<T>(&mut self, location: &'static Location, reason: fmt::Arguments<'_>) -> T
-        where
-            T: Termination,
-        {
-            debug_assert!(self.outcome.is_none());
-            self.outcome.replace(Outcome::Failed {
-                location,
+        pub fn fail(&mut self, location: &'static Location, reason: fmt::Arguments<'_>) -> ! {
+            self.fail_with_diff(location, reason, None)
+        }
+
+        pub fn fail_with_diff(
+            &mut self,
+            location: &'static Location,
+            reason: fmt::Arguments<'_>,
+            diff: Option<Diff>,
+        ) -> ! {
+            self.record_outcome(Outcome::Failed {
+                location: location.clone(),
                 reason: reason.to_string(),
+                diff,
             });
-            self.exit()
+            self.abort()
         }
     }
 }
@@ -324,6 +1047,18 @@ impl EnterSection {
     }
 }
 
+pub struct EnterDynamicSection {
+    enabled: bool,
+}
+
+impl EnterDynamicSection {
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+#[derive(Debug)]
 pub struct Section {
     pub id: SectionId,
     pub name: &'static str,
@@ -334,21 +1069,39 @@ pub struct Section {
 mod tests {
     use super::*;
     use crate::{
-        report::Summary,
+        report::{Seeds, Summary},
         test::{TestCase, TestDesc},
     };
     use futures::executor::block_on;
     use scoped_tls_async::{scoped_thread_local, ScopedKeyExt as _};
-    use std::cell::RefCell;
+    use std::{
+        cell::RefCell,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
 
     impl TestCase {
         async fn run<R>(&'static self, reporter: &mut R) -> TestCaseSummary
+        where
+            R: Reporter + Send + 'static,
+        {
+            self.run_with_options(reporter, TestOptions::default())
+                .await
+        }
+
+        async fn run_with_options<R>(
+            &'static self,
+            reporter: &mut R,
+            options: TestOptions,
+        ) -> TestCaseSummary
         where
             R: Reporter + Send + 'static,
         {
             let mut inner = TestInner {
                 desc: &self.desc,
-                plans: self.plans,
+                plans: self.plans.iter().collect(),
+                globals: Acquired::default(),
+                fixtures: FixtureStore::default(),
+                options,
             };
             match self.testfn {
                 TestFn::Async(f) => inner.run_async(reporter, f).await,
@@ -370,8 +1123,10 @@ mod tests {
     struct NullReporter;
 
     impl Reporter for NullReporter {
-        fn test_run_starting(&self, _: &[&TestCase]) {}
-        fn test_run_ended(&self, _: &Summary) {}
+        fn test_iteration_starting(&self, _: usize) {}
+        fn test_iterations_ended(&self, _: usize, _: &[(usize, Seeds)]) {}
+        fn test_run_starting(&self, _: &[&TestCase], _: Seeds) {}
+        fn test_run_ended(&self, _: &Summary, _: Seeds) {}
         fn test_case_starting(&self, _: &TestDesc) {}
         fn test_case_ended(&self, _: &TestCaseSummary) {}
     }
@@ -453,6 +1208,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shuffled_sections() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            section!(ctx, "section1", {
+                append_history(ctx, "section1");
+            });
+            section!(ctx, "section2", {
+                append_history(ctx, "section2");
+            });
+            section!(ctx, "section3", {
+                append_history(ctx, "section3");
+            });
+            section!(ctx, "section4", {
+                append_history(ctx, "section4");
+            });
+        }
+
+        let run_shuffled = |seed| {
+            let history = RefCell::new(vec![]);
+            let options = TestOptions {
+                shuffle_seed: Some(seed),
+                ..TestOptions::default()
+            };
+            let _summary = block_on(HISTORY.set_async(
+                &history,
+                test_case.run_with_options(&mut NullReporter, options),
+            ));
+            history.into_inner()
+        };
+
+        let history = run_shuffled(1);
+        let mut sorted = history.clone();
+        sorted.sort();
+        assert_eq!(sorted, run(test_case));
+
+        // the order is determined only by the seed.
+        assert_eq!(run_shuffled(1), history);
+        assert_ne!(run_shuffled(2), history);
+    }
+
     #[test]
     fn nested_section() {
         #[crate::test]
@@ -505,6 +1302,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sections_in_closures() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        async fn test_case(ctx: &mut Context<'_>) {
+            append_history(ctx, "setup");
+
+            async {
+                section!(ctx, "section1", {
+                    append_history(ctx, "section1");
+                });
+            }
+            .await;
+
+            let mut closure = |ctx: &mut Context<'_>| {
+                section!(ctx, "section2", {
+                    append_history(ctx, "section2");
+                });
+            };
+            closure(ctx);
+            closure(ctx);
+
+            append_history(ctx, "teardown");
+        }
+
+        let history = run(test_case);
+        assert_eq!(
+            history,
+            vec![
+                // phase 1
+                ("setup", None),
+                ("section1", Some("section1")),
+                ("teardown", None),
+                // phase 2
+                ("setup", None),
+                ("section2", Some("section2")),
+                ("teardown", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn dynamic_sections() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            append_history(ctx, "setup");
+
+            section!(ctx, "section1", {
+                for &i in &["i = 0", "i = 1"] {
+                    dynamic_section!(ctx, i, {
+                        append_history(ctx, i);
+                        for &j in &["j = 0", "j = 1"] {
+                            dynamic_section!(ctx, j, {
+                                append_history(ctx, j);
+                            });
+                        }
+                    });
+                }
+            });
+
+            section!(ctx, "section2", {
+                append_history(ctx, "section2");
+            });
+        }
+
+        let history = run(test_case);
+        assert_eq!(
+            history,
+            vec![
+                ("setup", None),
+                ("i = 0", Some("section1")),
+                ("j = 0", Some("section1")),
+                ("setup", None),
+                ("i = 0", Some("section1")),
+                ("j = 1", Some("section1")),
+                ("setup", None),
+                ("i = 1", Some("section1")),
+                ("j = 0", Some("section1")),
+                ("setup", None),
+                ("i = 1", Some("section1")),
+                ("j = 1", Some("section1")),
+                ("setup", None),
+                ("section2", Some("section2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn nondeterministic_dynamic_sections() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            for _ in 0..2 {
+                let name = format!("count = {}", COUNT.fetch_add(1, Ordering::SeqCst));
+                dynamic_section!(ctx, name, {
+                    append_history(ctx, "dynamic");
+                });
+            }
+        }
+
+        let history = RefCell::new(vec![]);
+        let summary = block_on(HISTORY.set_async(&history, test_case.run(&mut NullReporter)));
+        assert_eq!(history.into_inner().len(), 2);
+        match summary.outcome {
+            Outcome::Errored(ref err) => {
+                assert!(err.to_string().contains("must be the same in every run"))
+            }
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
     #[test]
     fn smoke_async() {
         #[crate::test]
@@ -565,4 +1476,192 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn skip_in_helper() {
+        fn helper(ctx: &mut Context<'_>) {
+            append_history(ctx, "helper");
+            crate::skip!(ctx, "skipped in helper");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            section!(ctx, "section1", {
+                helper(ctx);
+                append_history(ctx, "unreachable");
+            });
+        }
+
+        let history = RefCell::new(vec![]);
+        let summary = block_on(HISTORY.set_async(&history, test_case.run(&mut NullReporter)));
+        assert_eq!(history.into_inner(), vec![("helper", Some("section1"))]);
+        assert_eq!(summary.section_path, vec!["section1"]);
+        match summary.outcome {
+            Outcome::Skipped { ref reason, .. } => assert_eq!(reason, "skipped in helper"),
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn multiple_failures() {
+        fn helper(ctx: &mut Context<'_>) {
+            crate::fail!(ctx, "first failure");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| helper(ctx)));
+            crate::skip!(ctx, "ignored skip");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case2(ctx: &mut Context<'_>) {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| helper(ctx)));
+            crate::fail!(ctx, "second failure");
+        }
+
+        match block_on(test_case.run(&mut NullReporter)).outcome {
+            Outcome::Failed { ref reason, .. } => assert_eq!(reason, "first failure"),
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+        match block_on(test_case2.run(&mut NullReporter)).outcome {
+            Outcome::Failed { ref reason, .. } => {
+                assert!(reason.starts_with("first failure\nalso failed at "));
+                assert!(reason.ends_with(": second failure"));
+            }
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn failure_then_panic_or_error() {
+        fn helper(ctx: &mut Context<'_>) {
+            crate::fail!(ctx, "first failure");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case(ctx: &mut Context<'_>) {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| helper(ctx)));
+            panic!("explicit panic");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn test_case2(ctx: &mut Context<'_>) -> anyhow::Result<()> {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| helper(ctx)));
+            Err(anyhow::anyhow!("returned error"))
+        }
+
+        match block_on(test_case.run(&mut NullReporter)).outcome {
+            Outcome::Failed { ref reason, .. } => {
+                assert!(reason.starts_with("first failure\nalso panicked: "));
+                assert!(reason.contains("explicit panic"));
+            }
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+        match block_on(test_case2.run(&mut NullReporter)).outcome {
+            Outcome::Failed { ref reason, .. } => {
+                assert_eq!(
+                    reason,
+                    "first failure\nalso returned an error: returned error"
+                );
+            }
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn xfail() {
+        #[crate::test]
+        #[rye(crate = crate)]
+        #[rye(xfail = "known bug")]
+        fn failing(ctx: &mut Context<'_>) {
+            crate::fail!(ctx, "failed");
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        #[rye(xfail = "known bug")]
+        fn passing(ctx: &mut Context<'_>) {
+            append_history(ctx, "passing");
+        }
+
+        let summary = block_on(failing.run(&mut NullReporter));
+        assert!(!summary.outcome.is_failed());
+        match summary.outcome {
+            Outcome::ExpectedFailure(ref outcome) => match **outcome {
+                Outcome::Failed { ref reason, .. } => assert_eq!(reason, "failed"),
+                ref outcome => panic!("unexpected outcome: {:?}", outcome),
+            },
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        // the unexpected pass is not retried even if the retries are enabled.
+        let history = RefCell::new(vec![]);
+        let options = TestOptions {
+            retries: 2,
+            ..TestOptions::default()
+        };
+        let summary = block_on(HISTORY.set_async(
+            &history,
+            passing.run_with_options(&mut NullReporter, options),
+        ));
+        assert!(summary.outcome.is_failed());
+        match summary.outcome {
+            Outcome::UnexpectedPass { reason } => assert_eq!(reason, "known bug"),
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+        assert_eq!(history.into_inner(), vec![("passing", None)]);
+    }
+
+    #[test]
+    fn temp_dir_cleanup() {
+        thread_local! {
+            static TEMP_DIR: RefCell<Option<PathBuf>> = RefCell::new(None);
+        }
+
+        fn save_temp_dir(ctx: &mut Context<'_>) {
+            let temp_dir = ctx.temp_dir().unwrap();
+            fs::write(temp_dir.join("file"), "content").unwrap();
+            TEMP_DIR.with(|saved| saved.replace(Some(temp_dir)));
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn passing(ctx: &mut Context<'_>) {
+            save_temp_dir(ctx);
+        }
+
+        #[crate::test]
+        #[rye(crate = crate)]
+        fn failing(ctx: &mut Context<'_>) {
+            save_temp_dir(ctx);
+            crate::fail!(ctx, "failed");
+        }
+
+        let run = |test_case: &'static TestCase, keep_temp| {
+            let options = TestOptions {
+                keep_temp,
+                ..TestOptions::default()
+            };
+            block_on(test_case.run_with_options(&mut NullReporter, options));
+            TEMP_DIR.with(|saved| saved.borrow_mut().take()).unwrap()
+        };
+
+        let temp_dir = run(passing, false);
+        assert!(!temp_dir.exists());
+
+        // the directory is kept with --keep-temp, or when the test case failed.
+        let temp_dir = run(passing, true);
+        assert!(temp_dir.join("file").is_file());
+        fs::remove_dir_all(&temp_dir).unwrap();
+
+        let temp_dir = run(failing, false);
+        assert!(temp_dir.join("file").is_file());
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
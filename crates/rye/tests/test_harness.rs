@@ -1,11 +1,45 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 rye::test_harness!();
 
 #[rye::test_main]
 async fn test_main(sess: &mut rye::Session<'_>) -> anyhow::Result<()> {
-    sess.run().await?;
+    sess.register_global(
+        rye::Global::new(|| async { Ok(SharedCounter::default()) }).teardown(
+            |counter| async move {
+                TORN_DOWN_COUNT.store(counter.0.into_inner(), Ordering::SeqCst);
+            },
+        ),
+    );
+    let report = sess.run().await?;
+
+    // the outcome of each test case is exposed through its status and message.
+    anyhow::ensure!(
+        report.xfailed().iter().all(|result| {
+            result.status() == rye::TestStatus::ExpectedFailure && result.message().is_some()
+        }),
+        "the expected failures are not reported with their status"
+    );
+
+    // the global value must be torn down after all test cases using it are completed.
+    let acquired = ACQUIRED_COUNT.load(Ordering::SeqCst);
+    let torn_down = TORN_DOWN_COUNT.load(Ordering::SeqCst);
+    anyhow::ensure!(
+        torn_down == acquired,
+        "the global value is not torn down correctly (acquired {} times, but torn down with {})",
+        acquired,
+        torn_down,
+    );
+
     Ok(())
 }
 
+static ACQUIRED_COUNT: AtomicUsize = AtomicUsize::new(0);
+static TORN_DOWN_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct SharedCounter(AtomicUsize);
+
 macro_rules! require {
     ($ctx:ident, $e:expr) => {{
         if !($e) {
@@ -52,6 +86,22 @@ fn nested(ctx: &mut rye::Context<'_>) {
     });
 }
 
+#[rye::test]
+fn dynamic_sections(ctx: &mut rye::Context<'_>) {
+    let mut vec = vec![0usize; 5];
+
+    for n in &[0, 3, 10] {
+        rye::dynamic_section!(ctx, format_args!("resizing to {}", n), {
+            vec.resize(*n, 0);
+
+            require!(ctx, vec.len() == *n);
+            require!(ctx, vec.capacity() >= 5);
+        });
+    }
+
+    require!(ctx, vec.len() != 5);
+}
+
 #[rye::test]
 async fn case_async(ctx: &mut rye::Context<'_>) {
     let mut vec = vec![0usize; 5];
@@ -67,6 +117,28 @@ async fn case_async(ctx: &mut rye::Context<'_>) {
     });
 }
 
+#[rye::test]
+async fn sections_in_async_block(ctx: &mut rye::Context<'_>) {
+    let mut vec = vec![0usize; 5];
+
+    let resize = |ctx: &mut rye::Context<'_>, vec: &mut Vec<usize>| {
+        section!(ctx, "resizing in closure", {
+            vec.resize(10, 0);
+            require!(ctx, vec.len() == 10);
+        });
+    };
+    resize(ctx, &mut vec);
+
+    async {
+        section!(ctx, "resizing in async block", {
+            futures::future::ready(()).await;
+            vec.resize(0, 0);
+            require!(ctx, vec.is_empty());
+        });
+    }
+    .await;
+}
+
 #[rye::test(?Send)]
 async fn case_async_nosend(ctx: &mut rye::Context<'_>) {
     let mut vec = vec![0usize; 5];
@@ -151,6 +223,188 @@ fn expensive_test(ctx: &mut rye::Context<'_>) {
     // do expensive tests ...
 }
 
+#[rye::test]
+fn return_boxed_error(ctx: &mut rye::Context<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let n: i32 = "42".parse()?;
+    require!(ctx, n == 42);
+    Ok(())
+}
+
+#[rye::test]
+fn return_exit_code(ctx: &mut rye::Context<'_>) -> i32 {
+    require!(ctx, 1 + 1 == 2);
+    0
+}
+
+#[rye::test]
+fn return_skipped_exit_code(_: &mut rye::Context<'_>) -> i32 {
+    77
+}
+
+enum Status {
+    Ok,
+    Unsupported(&'static str),
+}
+
+impl rye::Termination for Status {
+    fn into_result(self) -> rye::TestResult {
+        match self {
+            Status::Ok => rye::TestResult::Passed,
+            Status::Unsupported(reason) => rye::TestResult::Skipped(reason.into()),
+        }
+    }
+}
+
+#[rye::test]
+fn return_custom_status(ctx: &mut rye::Context<'_>) -> Result<Status, std::num::ParseIntError> {
+    let n: i32 = "42".parse()?;
+    require!(ctx, n == 42);
+    Ok(Status::Ok)
+}
+
+#[rye::test]
+fn return_custom_skip(_: &mut rye::Context<'_>) -> Status {
+    Status::Unsupported("returned a custom status")
+}
+
+fn skip_unless_expensive(ctx: &mut rye::Context<'_>) {
+    if std::env::var("RUN_EXPENSIVE_TESTS").is_err() {
+        rye::skip!(ctx, "set RUN_EXPENSIVE_TESTS=true to be enabled");
+    }
+}
+
+#[rye::test]
+fn expensive_test_helper(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    skip_unless_expensive(ctx);
+
+    // do expensive tests ...
+
+    Ok(())
+}
+
+fn is_not_expensive() -> bool {
+    std::env::var("RUN_EXPENSIVE_TESTS").is_err()
+}
+
+#[rye::test]
+#[rye(skip_if = is_not_expensive)]
+fn expensive_test_skip_if(_: &mut rye::Context<'_>) {
+    // do expensive tests ...
+}
+
+#[rye::test]
+#[rye(require_env = "RYE_UNDEFINED_VARIABLE")]
+#[rye(require_cmd = "rye-undefined-command")]
+fn require_env_and_cmd(_: &mut rye::Context<'_>) {
+    unreachable!("the required variable and command are not available");
+}
+
+#[rye::test]
+#[rye(require_env = "PATH")]
+#[rye(require_cmd = "cargo")]
+fn required_env_and_cmd_exist(ctx: &mut rye::Context<'_>) {
+    require!(ctx, std::env::var_os("PATH").is_some());
+}
+
+#[rye::test]
+#[rye(xfail = "the known bug of resizing")]
+fn xfail(ctx: &mut rye::Context<'_>) {
+    let mut vec = vec![0usize; 5];
+    vec.resize(10, 0);
+    require!(ctx, vec.len() == 5);
+}
+
+#[rye::test]
+#[rye(xfail = "the known bug of isolated test")]
+#[rye(isolate)]
+fn xfail_isolated(ctx: &mut rye::Context<'_>) {
+    rye::fail!(ctx, "failed in the subprocess");
+}
+
+#[rye::test]
+fn approx(ctx: &mut rye::Context<'_>) {
+    use rye::approx;
+
+    let sum: f64 = (0..10).map(|_| 0.1).sum();
+    require!(ctx, sum != 1.0);
+    require!(ctx, sum == approx(1.0));
+
+    let normalized: Vec<f32> = [3.0_f32, 4.0].iter().map(|x| x / 5.0).collect();
+    if let Err(err) = approx([0.6, 0.8]).margin(1e-6).check(&normalized) {
+        rye::fail!(ctx, "{}", err);
+    }
+}
+
+#[rye::test]
+fn matchers(ctx: &mut rye::Context<'_>) {
+    use rye::matchers::{contains, err, is_sorted, message, starts_with, unordered_equals};
+
+    let s = String::from("foobaz");
+    rye::require_that!(ctx, s, starts_with("foo") & !contains("bar"));
+
+    let mut values = vec![3, 1, 2];
+    values.sort();
+    rye::require_that!(ctx, values, is_sorted() & unordered_equals(vec![2, 3, 1]));
+
+    let res = "x".parse::<i32>();
+    rye::require_that!(ctx, res, err(message(contains("invalid digit"))));
+}
+
+#[rye::test]
+fn require_eq(ctx: &mut rye::Context<'_>) {
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        name: String,
+        values: Vec<u32>,
+    }
+
+    let config = Config {
+        name: "foo".into(),
+        values: vec![1, 2, 3],
+    };
+    rye::require_eq!(ctx, config.values.len(), 3);
+    rye::require_eq!(ctx, config.name, "foo");
+    rye::require_eq!(
+        ctx,
+        config,
+        Config {
+            name: "foo".into(),
+            values: vec![1, 2, 3],
+        }
+    );
+}
+
+#[rye::test]
+fn require_panics_and_err(ctx: &mut rye::Context<'_>) {
+    use rye::matchers::contains;
+
+    let v = vec![1, 2, 3];
+    rye::require_panics!(ctx, v[3]);
+    rye::require_panics!(ctx, v[3], contains("out of bounds"));
+
+    let err = rye::require_err!(ctx, "x".parse::<i32>());
+    require!(ctx, err.to_string().contains("invalid digit"));
+
+    rye::require_err!(ctx, std::fs::read("/nonexistent/file"), e if e.kind() == std::io::ErrorKind::NotFound);
+
+    let res: anyhow::Result<()> = Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+    rye::require_err!(ctx, res, type = std::io::Error);
+}
+
+#[rye::test]
+async fn require_panics_async(ctx: &mut rye::Context<'_>) {
+    use rye::matchers::starts_with;
+
+    rye::require_panics!(
+        ctx,
+        async {
+            futures::future::ready(()).await;
+            panic!("panicked in the async block");
+        },
+        starts_with("panicked")
+    );
+}
+
 #[rye::test]
 fn expensive_test_fallible(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
     if std::env::var("RUN_EXPENSIVE_TESTS").is_err() {
@@ -195,3 +449,191 @@ fn smoke_macros_fallible(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[rye::test]
+#[rye(global = SharedCounter)]
+async fn global_async(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let counter = ctx.global::<SharedCounter>()?;
+    counter.0.fetch_add(1, Ordering::SeqCst);
+    ACQUIRED_COUNT.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+#[rye::test]
+#[rye(global = SharedCounter)]
+fn global_blocking(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let counter = ctx.global::<SharedCounter>()?;
+    counter.0.fetch_add(1, Ordering::SeqCst);
+    ACQUIRED_COUNT.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+#[rye::fixture]
+fn numbers() -> Vec<usize> {
+    vec![1, 2, 3]
+}
+
+#[rye::fixture]
+#[rye(scope = test)]
+#[rye(teardown = close_log)]
+async fn log() -> Vec<&'static str> {
+    vec!["opened"]
+}
+
+async fn close_log(log: Vec<&'static str>) {
+    assert_eq!(log.len(), 3);
+}
+
+#[rye::fixture]
+#[rye(scope = session)]
+fn config() -> String {
+    "rye".into()
+}
+
+#[rye::test]
+fn fixture_blocking(ctx: &mut rye::Context<'_>, numbers: &mut Vec<usize>, config: &String) {
+    require!(ctx, numbers.len() == 3);
+    require!(ctx, config == "rye");
+
+    section!(
+        ctx,
+        "section-scoped values are recreated for each section",
+        {
+            numbers.push(4);
+            require!(ctx, numbers.len() == 4);
+        }
+    );
+
+    section!(ctx, "another section", {
+        require!(ctx, numbers.len() == 3);
+    });
+}
+
+#[rye::test]
+async fn fixture_async(ctx: &mut rye::Context<'_>, log: &mut Vec<&'static str>) {
+    section!(ctx, "test-scoped values are shared among sections", {
+        log.push("section 1");
+    });
+
+    section!(ctx, "another section", {
+        log.push("section 2");
+    });
+}
+
+#[rye::fixture]
+#[rye(scope = test)]
+fn attempts() -> Vec<usize> {
+    vec![]
+}
+
+#[rye::test]
+#[rye(retries = 1)]
+fn fixture_retried(ctx: &mut rye::Context<'_>, attempts: &mut Vec<usize>) {
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+    let attempt = ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+    attempts.push(attempt);
+    // test-scoped values are set up again for the retry.
+    require!(ctx, attempts.len() == 1);
+    if attempt == 0 {
+        rye::fail!(ctx, "failed at the first attempt");
+    }
+}
+
+#[rye::test]
+async fn fixture_renamed(
+    ctx: &mut rye::Context<'_>,
+    #[rye(fixture = numbers)] values: &Vec<usize>,
+) {
+    require!(ctx, values.iter().sum::<usize>() == 6);
+}
+
+#[rye::test]
+fn temp_dir(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let dir = ctx.temp_dir()?;
+    require!(ctx, dir.is_dir());
+    require!(ctx, ctx.temp_dir()? == dir);
+
+    section!(ctx, "write a file", {
+        std::fs::write(dir.join("foo.txt"), "foo")?;
+        require!(ctx, dir.join("foo.txt").is_file());
+    });
+
+    section!(ctx, "each section uses another directory", {
+        require!(ctx, !dir.join("foo.txt").exists());
+    });
+
+    Ok(())
+}
+
+#[rye::test]
+fn rng(ctx: &mut rye::Context<'_>) {
+    use rand::Rng as _;
+
+    let n: u32 = ctx.rng().gen_range(0, 10);
+    require!(ctx, n < 10);
+
+    section!(ctx, "each section uses another generator", {
+        let n: u32 = ctx.rng().gen_range(10, 20);
+        require!(ctx, n >= 10 && n < 20);
+    });
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[rye::test]
+fn snapshot(ctx: &mut rye::Context<'_>) {
+    rye::snapshot!(ctx, Point { x: 1, y: 2 });
+
+    section!(ctx, "display", {
+        rye::snapshot!(ctx, display = "multi\nline\ntext");
+    });
+}
+
+#[rye::test]
+#[rye(isolate)]
+fn isolated(ctx: &mut rye::Context<'_>) {
+    require!(ctx, std::env::var_os("RYE_ISOLATED_CHILD").is_some());
+}
+
+#[rye::test]
+#[rye(xfail = "the test process is aborted")]
+#[rye(isolate)]
+fn isolated_abort(_: &mut rye::Context<'_>) {
+    std::process::abort();
+}
+
+#[rye::test]
+#[rye(retries = 2)]
+fn flaky(ctx: &mut rye::Context<'_>) {
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+    if ATTEMPTS.fetch_add(1, Ordering::SeqCst) == 0 {
+        rye::fail!(ctx, "failed at the first attempt");
+    }
+}
+
+static SERIAL_IN_USE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+async fn use_serial_resource(ctx: &mut rye::Context<'_>) {
+    use futures_test::future::FutureTestExt as _;
+
+    require!(ctx, !SERIAL_IN_USE.swap(true, Ordering::SeqCst));
+    async {}.pending_once().await;
+    SERIAL_IN_USE.store(false, Ordering::SeqCst);
+}
+
+#[rye::test]
+#[rye(serial(group = "shared"))]
+async fn serial_1(ctx: &mut rye::Context<'_>) {
+    use_serial_resource(ctx).await;
+}
+
+#[rye::test]
+#[rye(serial(group = "shared"))]
+async fn serial_2(ctx: &mut rye::Context<'_>) {
+    use_serial_resource(ctx).await;
+}
@@ -0,0 +1,246 @@
+//! The tests for the options of test sessions.
+//!
+//! Each test runs the test cases in the `fixtures` module by executing this
+//! binary as a subprocess with the environment variable `FIXTURE_ENV` set,
+//! and checks the events written by the subprocess in JSON.
+
+use serde_json::Value;
+use std::{env, process::Command};
+
+rye::test_harness!();
+
+#[rye::test_main]
+async fn test_main(sess: &mut rye::Session<'_>) -> anyhow::Result<()> {
+    sess.run().await?;
+    Ok(())
+}
+
+/// The environment variable to enable the test cases in `fixtures`.
+const FIXTURE_ENV: &str = "RYE_TEST_SESSION_FIXTURE";
+
+macro_rules! require {
+    ($ctx:ident, $e:expr) => {{
+        if !($e) {
+            rye::fail!($ctx, concat!("assertion failed: ", stringify!($e)));
+        }
+    }};
+}
+
+/// The test cases run only in the subprocesses.
+mod fixtures {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[rye::test]
+    #[rye(require_env = "RYE_TEST_SESSION_FIXTURE")]
+    fn passing(_: &mut rye::Context<'_>) {}
+
+    #[rye::test]
+    #[rye(require_env = "RYE_TEST_SESSION_FIXTURE")]
+    fn fails_at_third_run(ctx: &mut rye::Context<'_>) {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        if RUNS.fetch_add(1, Ordering::SeqCst) == 2 {
+            rye::fail!(ctx, "failed at the third run");
+        }
+    }
+
+    #[rye::test]
+    #[rye(require_env = "RYE_TEST_SESSION_FIXTURE")]
+    fn sections(ctx: &mut rye::Context<'_>) {
+        section!(ctx, "passing", {
+            entered("passing");
+        });
+        section!(ctx, "failing", {
+            entered("failing");
+            rye::fail!(ctx, "failed in the section");
+        });
+    }
+
+    #[rye::test]
+    #[rye(require_env = "RYE_TEST_SESSION_FIXTURE")]
+    #[rye(xfail = "known bug")]
+    fn expected_failure(ctx: &mut rye::Context<'_>) {
+        rye::fail!(ctx, "failed as expected");
+    }
+
+    #[rye::test]
+    #[rye(require_env = "RYE_TEST_SESSION_FIXTURE")]
+    #[rye(xfail = "fixed bug")]
+    fn unexpected_pass(_: &mut rye::Context<'_>) {}
+
+    /// Write an event to tell the parent process which section is entered.
+    fn entered(section: &str) {
+        println!(
+            r#"{{"type":"fixture","event":"section","name":"{}"}}"#,
+            section
+        );
+    }
+}
+
+/// Run the test cases in `fixtures` as a subprocess, and return whether it
+/// succeeded and the events written by it.
+fn run_fixtures(args: &[&str]) -> anyhow::Result<(bool, Vec<Value>)> {
+    let output = Command::new(env::current_exe()?)
+        .args(args)
+        .args(&["--format", "json"])
+        .env(FIXTURE_ENV, "1")
+        .env_remove("RYE_SEED")
+        .output()?;
+    let events = String::from_utf8(output.stdout)?
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    Ok((output.status.success(), events))
+}
+
+/// Return the events of the specified type.
+fn find_events<'a>(events: &'a [Value], ty: &str, event: &str) -> Vec<&'a Value> {
+    events
+        .iter()
+        .filter(|e| e["type"] == ty && e["event"] == event)
+        .collect()
+}
+
+// The subprocesses share the state file of failed test cases, so the tests
+// running them must not be run concurrently.
+
+#[rye::test]
+#[rye(serial)]
+fn repeat(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let (success, events) = run_fixtures(&[
+        "fixtures::passing",
+        "--exact",
+        "--repeat=3",
+        "--seed=42",
+        "--shuffle-seed=7",
+    ])?;
+    require!(ctx, success);
+
+    let runs = find_events(&events, "suite", "started");
+    require!(ctx, runs.len() == 3);
+    require!(ctx, find_events(&events, "test", "ok").len() == 3);
+
+    // the first iteration uses the specified seeds, and the others use the derived ones.
+    require!(ctx, runs[0]["seed"] == 42 && runs[0]["shuffle_seed"] == 7);
+    require!(ctx, runs[1]["seed"] != runs[0]["seed"]);
+    require!(ctx, runs[2]["seed"] != runs[1]["seed"]);
+    require!(ctx, runs[1]["shuffle_seed"] != runs[0]["shuffle_seed"]);
+
+    let ended = find_events(&events, "iterations", "ok");
+    require!(ctx, ended.len() == 1);
+    require!(ctx, ended[0]["iterations"] == 3);
+    require!(ctx, ended[0]["failed_iterations"] == serde_json::json!([]));
+
+    Ok(())
+}
+
+#[rye::test]
+#[rye(serial)]
+fn until_failure(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let (success, events) =
+        run_fixtures(&["fixtures::fails_at_third_run", "--exact", "--until-failure"])?;
+    require!(ctx, !success);
+
+    // the test cases are not run again after the failing iteration.
+    let runs = find_events(&events, "suite", "started");
+    require!(ctx, runs.len() == 3);
+    require!(ctx, find_events(&events, "test", "failed").len() == 1);
+
+    let ended = find_events(&events, "iterations", "failed");
+    require!(ctx, ended.len() == 1);
+    require!(ctx, ended[0]["iterations"] == 3);
+
+    // the seed of the failing iteration is reported to reproduce it.
+    let failed = &ended[0]["failed_iterations"];
+    require!(ctx, failed.as_array().map_or(0, Vec::len) == 1);
+    require!(ctx, failed[0]["iteration"] == 3);
+    require!(ctx, failed[0]["seed"] == runs[2]["seed"]);
+
+    Ok(())
+}
+
+#[rye::test]
+#[rye(serial)]
+fn rerun_failed(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let (success, events) = run_fixtures(&["fixtures::sections", "--exact"])?;
+    require!(ctx, !success);
+    require!(ctx, find_events(&events, "fixture", "section").len() == 2);
+
+    // only the failing section is run again.
+    let (success, events) = run_fixtures(&["fixtures::sections", "--exact", "--failed"])?;
+    require!(ctx, !success);
+    let entered = find_events(&events, "fixture", "section");
+    require!(ctx, entered.len() == 1);
+    require!(ctx, entered[0]["name"] == "failing");
+
+    let failed = find_events(&events, "test", "failed");
+    require!(ctx, failed.len() == 1);
+    require!(
+        ctx,
+        failed[0]["section_path"] == serde_json::json!(["failing"])
+    );
+
+    Ok(())
+}
+
+#[rye::test]
+#[rye(serial)]
+fn xfail_events(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let (success, events) = run_fixtures(&[
+        "fixtures::expected_failure",
+        "fixtures::unexpected_pass",
+        "--exact",
+    ])?;
+    // the unexpected pass is treated as a failure.
+    require!(ctx, !success);
+
+    let xfailed = find_events(&events, "test", "xfailed");
+    require!(ctx, xfailed.len() == 1);
+    require!(ctx, xfailed[0]["name"] == "fixtures::expected_failure");
+    require!(ctx, xfailed[0]["message"] == "failed as expected");
+    require!(ctx, xfailed[0]["location"]["file"] == file!());
+
+    let xpassed = find_events(&events, "test", "xpassed");
+    require!(ctx, xpassed.len() == 1);
+    require!(ctx, xpassed[0]["name"] == "fixtures::unexpected_pass");
+    require!(
+        ctx,
+        xpassed[0]["message"]
+            .as_str()
+            .map_or(false, |message| message.contains("fixed bug"))
+    );
+
+    let suite = find_events(&events, "suite", "failed");
+    require!(ctx, suite.len() == 1);
+    require!(ctx, suite[0]["xfailed"] == 1 && suite[0]["xpassed"] == 1);
+    require!(ctx, suite[0]["failed"] == 0);
+
+    Ok(())
+}
+
+#[rye::test]
+fn list_json(ctx: &mut rye::Context<'_>) -> anyhow::Result<()> {
+    let (success, tests) = run_fixtures(&["fixtures::sections", "--exact", "--list"])?;
+    require!(ctx, success);
+    require!(ctx, tests.len() == 1);
+
+    let test = &tests[0];
+    require!(ctx, test["type"] == "test");
+    require!(ctx, test["name"] == "fixtures::sections");
+    require!(ctx, test["kind"] == "blocking");
+    require!(ctx, test["ignored"] == false);
+    require!(ctx, test["location"]["file"] == file!());
+    let skip_conditions = serde_json::json!([{ "kind": "require_env", "value": FIXTURE_ENV }]);
+    require!(ctx, test["skip_conditions"] == skip_conditions);
+    require!(ctx, test["xfail"].is_null());
+
+    let sections = &test["sections"];
+    require!(ctx, sections.as_array().map_or(0, Vec::len) == 2);
+    require!(ctx, sections[0]["name"] == "passing");
+    require!(ctx, sections[0]["path"] == "fixtures::sections/passing");
+    require!(ctx, sections[1]["name"] == "failing");
+    require!(ctx, sections[1]["path"] == "fixtures::sections/failing");
+    require!(ctx, sections[1]["sections"] == serde_json::json!([]));
+
+    Ok(())
+}
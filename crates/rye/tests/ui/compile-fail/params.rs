@@ -7,3 +7,16 @@ fn invalid_path_type(_: &mut rye::Context<'_>) {}
 #[rye::test]
 #[rye(the_quick_fox = "lazy")]
 fn unknown_param(_: &mut rye::Context<'_>) {}
+
+#[rye::test]
+#[rye(xfail = "known bug")]
+#[rye(retries = 2)]
+fn xfail_with_retries(_: &mut rye::Context<'_>) {}
+
+#[rye::test]
+#[rye(serial(group = ""))]
+fn empty_group(_: &mut rye::Context<'_>) {}
+
+#[rye::test]
+#[rye(resource = "")]
+fn empty_resource(_: &mut rye::Context<'_>) {}
@@ -6,3 +6,41 @@ fn section_in_async_block(cx: &mut rye::Context<'_>) {
         section!(cx, "section", {});
     });
 }
+
+#[rye::test]
+fn section_in_async_block_in_loop(cx: &mut rye::Context<'_>) {
+    for _ in 0..10 {
+        let _ = std::convert::identity(async {
+            section!(cx, "section", {});
+        });
+    }
+}
+
+#[rye::test]
+async fn section_in_async_block_not_awaited(cx: &mut rye::Context<'_>) {
+    let future = async {
+        section!(cx, "section", {});
+    };
+    drop(future);
+}
+
+#[rye::test]
+async fn section_in_async_block_in_if(cx: &mut rye::Context<'_>) {
+    if std::env::var_os("RYE_TEST").is_some() {
+        async {
+            section!(cx, "section", {});
+        }
+        .await;
+    }
+}
+
+#[rye::test]
+async fn section_in_async_block_in_match_arm(cx: &mut rye::Context<'_>) {
+    match std::env::var_os("RYE_TEST") {
+        Some(..) => async {
+            section!(cx, "section", {});
+        }
+        .await,
+        None => (),
+    }
+}
@@ -6,3 +6,58 @@ fn section_in_closure(cx: &mut rye::Context<'_>) {
         section!(cx, "section", {});
     });
 }
+
+#[rye::test]
+fn section_in_closure_in_loop(cx: &mut rye::Context<'_>) {
+    loop {
+        std::convert::identity(|| {
+            section!(cx, "section", {});
+        });
+    }
+}
+
+#[rye::test]
+fn closure_called_in_section(cx: &mut rye::Context<'_>) {
+    let mut closure = |cx: &mut rye::Context<'_>| {
+        section!(cx, "section in closure", {});
+    };
+
+    section!(cx, "section", {
+        closure(cx);
+    });
+}
+
+#[rye::test]
+fn section_in_closure_for_each(cx: &mut rye::Context<'_>) {
+    (0..3).for_each(|_| {
+        section!(cx, "section", {});
+    });
+}
+
+#[rye::test]
+fn closure_passed_to_for_each(cx: &mut rye::Context<'_>) {
+    let closure = |_| {
+        section!(cx, "section", {});
+    };
+    (0..3).for_each(closure);
+}
+
+#[rye::test]
+fn closure_called_in_if(cx: &mut rye::Context<'_>) {
+    if std::env::var_os("RYE_TEST").is_some() {
+        (|| {
+            section!(cx, "section", {});
+        })();
+    }
+}
+
+#[rye::test]
+fn closure_called_in_match_arm(cx: &mut rye::Context<'_>) {
+    let mut closure = |cx: &mut rye::Context<'_>| {
+        section!(cx, "section in closure", {});
+    };
+    match std::env::var_os("RYE_TEST") {
+        Some(..) => closure(cx),
+        None => (),
+    }
+}
@@ -0,0 +1,12 @@
+fn main() {}
+
+#[rye::test]
+fn section_in_dynamic_section(cx: &mut rye::Context<'_>) {
+    for i in 0..10 {
+        rye::dynamic_section!(cx, format!("i = {}", i), {
+            if i % 2 == 0 {
+                section!(cx, "section", {});
+            }
+        });
+    }
+}